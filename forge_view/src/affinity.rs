@@ -0,0 +1,132 @@
+use crate::hosts::HostControl;
+use eframe::egui;
+use process::{CpuCoreUsage, Process};
+
+const AFFINITY_CPUS_PER_ROW: usize = 8;
+
+pub struct AffinityDialog {
+    pid: u32,
+    name: String,
+    // CPU ids with whether the process may run on them, ids skip offline CPUs
+    cpus: Vec<(usize, bool)>,
+}
+
+impl AffinityDialog {
+    pub fn new(proc_info: &Process, cpu_cores: &[CpuCoreUsage]) -> Self {
+        let cpus_allowed = proc_info.get_cpus_allowed();
+        Self {
+            pid: proc_info.get_pid(),
+            name: proc_info.get_name().clone(),
+            cpus: cpu_cores
+                .iter()
+                .map(|cpu_core| (cpu_core.get_id(), cpus_allowed.contains(&cpu_core.get_id())))
+                .collect(),
+        }
+    }
+}
+
+/// Formats CPU numbers the way the kernel prints them, e.g. `0-3,8`.
+pub fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut index = 0usize;
+    while index < cpus.len() {
+        let first = cpus[index];
+        while index + 1 < cpus.len() && cpus[index + 1] == cpus[index] + 1 {
+            index += 1;
+        }
+        match cpus[index] == first {
+            true => ranges.push(first.to_string()),
+            false => ranges.push(format!("{}-{}", first, cpus[index])),
+        }
+        index += 1;
+    }
+    ranges.join(",")
+}
+
+pub fn affinity_window(
+    ctx: &egui::Context,
+    control: &mut HostControl,
+    affinity_dialog: &mut Option<AffinityDialog>,
+    action_status: &mut Option<String>,
+) {
+    let Some(dialog) = affinity_dialog else {
+        return;
+    };
+    let mut open = true;
+    egui::Window::new(format!(
+        "CPU affinity - {} (PID {})",
+        dialog.name, dialog.pid
+    ))
+    .collapsible(false)
+    .resizable(false)
+    .open(&mut open)
+    .show(ctx, |ui| {
+        egui::Grid::new("Affinity CPUs").show(ui, |ui| {
+            for (index, (cpu, allowed)) in dialog.cpus.iter_mut().enumerate() {
+                ui.checkbox(allowed, format!("cpu{}", cpu));
+                if (index + 1) % AFFINITY_CPUS_PER_ROW == 0 {
+                    ui.end_row();
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui.button("All").clicked() {
+                dialog
+                    .cpus
+                    .iter_mut()
+                    .for_each(|(_, allowed)| *allowed = true);
+            }
+            if ui.button("Apply").clicked() {
+                let cpus: Vec<usize> = dialog
+                    .cpus
+                    .iter()
+                    .filter(|(_, allowed)| *allowed)
+                    .map(|(cpu, _)| *cpu)
+                    .collect();
+                *action_status = Some(match control.set_cpu_affinity(dialog.pid, &cpus) {
+                    Ok(()) => format!(
+                        "Set CPU affinity {} on PID {}",
+                        format_cpu_list(&cpus),
+                        dialog.pid
+                    ),
+                    Err(err) => format!("Error: {}", err),
+                });
+            }
+        });
+    });
+    if !open {
+        *affinity_dialog = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use process::parse_cpu_list;
+
+    fn round_trip(cpus: &[usize]) -> Vec<usize> {
+        parse_cpu_list(&format_cpu_list(cpus)).unwrap()
+    }
+
+    #[test]
+    fn ranges_and_single_cpus_round_trip() {
+        assert_eq!(format_cpu_list(&[0, 1, 2, 3, 8]), "0-3,8");
+        assert_eq!(format_cpu_list(&[1, 3, 4, 6]), "1,3-4,6");
+        assert_eq!(format_cpu_list(&[5]), "5");
+        for cpus in [
+            vec![0, 1, 2, 3, 8],
+            vec![1, 3, 4, 6],
+            vec![5],
+            vec![0, 2, 4],
+        ] {
+            assert_eq!(round_trip(&cpus), cpus);
+        }
+        assert_eq!(round_trip(&parse_cpu_list("0-63").unwrap()).len(), 64);
+    }
+
+    #[test]
+    fn empty_sets_round_trip() {
+        assert_eq!(format_cpu_list(&[]), "");
+        assert!(round_trip(&[]).is_empty());
+    }
+}
@@ -0,0 +1,89 @@
+use eframe::egui;
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+use process::{History, ProcessSample, SystemSample};
+use std::time::SystemTime;
+
+const CHART_HEIGHT: f32 = 120.0;
+
+// Seconds relative to `now`, so the newest sample sits at x = 0
+fn seconds_ago(now: SystemTime, timestamp: SystemTime) -> f64 {
+    -now.duration_since(timestamp)
+        .map(|age| age.as_secs_f64())
+        .unwrap_or(0f64)
+}
+
+// Relative to the latest refresh rather than the clock, so replayed recordings line up too
+fn latest_timestamp(history: &History) -> SystemTime {
+    history
+        .get_system_samples()
+        .back()
+        .map(|system_sample| system_sample.get_timestamp())
+        .unwrap_or_else(SystemTime::now)
+}
+
+fn history_plot(id: &str, history: &History) -> Plot {
+    Plot::new(id)
+        .height(CHART_HEIGHT)
+        .legend(Legend::default())
+        .include_x(-history.get_duration().as_secs_f64())
+        .include_x(0f64)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .x_axis_label("seconds ago")
+}
+
+pub fn system_history_chart(ui: &mut egui::Ui, history: &History) {
+    let now = latest_timestamp(history);
+    let samples = history.get_system_samples();
+    let usage_points = |value: fn(&SystemSample) -> f32| -> PlotPoints {
+        samples
+            .iter()
+            .map(|sample| {
+                [
+                    seconds_ago(now, sample.get_timestamp()),
+                    value(sample) as f64,
+                ]
+            })
+            .collect()
+    };
+    history_plot("System History", history)
+        .include_y(0f64)
+        .include_y(100f64)
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(usage_points(|sample| sample.get_cpu_used())).name("%CPU"));
+            plot_ui.line(Line::new(usage_points(|sample| sample.get_mem_used())).name("%MEM"));
+        });
+}
+
+pub fn process_history_chart(ui: &mut egui::Ui, history: &History, pid: u32) {
+    let Some(samples) = history.get_proc_samples(pid) else {
+        ui.label("No history recorded yet");
+        return;
+    };
+    let now = latest_timestamp(history);
+    let usage_points = |value: fn(&ProcessSample) -> f32| -> PlotPoints {
+        samples
+            .iter()
+            .map(|sample| {
+                [
+                    seconds_ago(now, sample.get_timestamp()),
+                    value(sample) as f64,
+                ]
+            })
+            .collect()
+    };
+    history_plot("Process Usage History", history)
+        .include_y(0f64)
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(usage_points(|sample| sample.get_cpu_used())).name("%CPU"));
+            plot_ui.line(Line::new(usage_points(|sample| sample.get_mem_used())).name("%MEM"));
+        });
+    history_plot("Process I/O History", history)
+        .include_y(0f64)
+        .y_axis_label("bytes/s")
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(usage_points(|sample| sample.get_read_rate())).name("Read/s"));
+            plot_ui.line(Line::new(usage_points(|sample| sample.get_write_rate())).name("Write/s"));
+        });
+}
@@ -0,0 +1,72 @@
+use eframe::egui;
+use egui_extras::{Column, TableBuilder};
+use process::{Process, Socket, SocketProtocol, SocketState};
+
+pub fn connections_layout(ui: &mut egui::Ui, system_list: &[(u32, Process)], show_unix: &mut bool) {
+    ui.checkbox(show_unix, "Show unix sockets");
+    let mut connections: Vec<(u32, &Process, &Socket)> = system_list
+        .iter()
+        .flat_map(|(pid, proc_info)| {
+            proc_info
+                .get_sockets()
+                .iter()
+                .map(move |socket| (*pid, proc_info, socket))
+        })
+        .filter(|(_, _, socket)| *show_unix || socket.get_protocol() != SocketProtocol::Unix)
+        .collect();
+    // Listening sockets first, like `ss -tulpn`, then everything else by protocol and address
+    connections.sort_by(|(pid_a, _, socket_a), (pid_b, _, socket_b)| {
+        (socket_a.get_state() != SocketState::Listen)
+            .cmp(&(socket_b.get_state() != SocketState::Listen))
+            .then(
+                socket_a
+                    .get_protocol()
+                    .as_str()
+                    .cmp(socket_b.get_protocol().as_str()),
+            )
+            .then(
+                socket_a
+                    .get_local_address()
+                    .cmp(socket_b.get_local_address()),
+            )
+            .then(pid_a.cmp(pid_b))
+    });
+    TableBuilder::new(ui)
+        .striped(true)
+        .column(Column::auto().resizable(true))
+        .column(Column::auto().resizable(true))
+        .column(Column::remainder().clip(true).resizable(true))
+        .column(Column::remainder().clip(true).resizable(true))
+        .column(Column::auto().resizable(true))
+        .column(Column::remainder().clip(true).resizable(true))
+        .header(20.0, |mut header| {
+            for title in ["Proto", "State", "Local", "Remote", "PID", "Process"] {
+                header.col(|ui| {
+                    ui.heading(title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(20.0, connections.len(), |mut row| {
+                let (pid, proc_info, socket) = connections[row.index()];
+                row.col(|ui| {
+                    ui.label(socket.get_protocol().as_str());
+                });
+                row.col(|ui| {
+                    ui.label(socket.get_state().as_str());
+                });
+                row.col(|ui| {
+                    ui.label(socket.get_local_address());
+                });
+                row.col(|ui| {
+                    ui.label(socket.get_remote_address());
+                });
+                row.col(|ui| {
+                    ui.label(pid.to_string());
+                });
+                row.col(|ui| {
+                    ui.label(proc_info.get_name());
+                });
+            });
+        });
+}
@@ -0,0 +1,75 @@
+use crate::affinity::format_cpu_list;
+use crate::charts::process_history_chart;
+use anyhow::Result;
+use eframe::egui;
+use egui_extras::{Column, TableBuilder};
+use process::{History, OpenFile, Process};
+
+pub fn details_layout(
+    ui: &mut egui::Ui,
+    proc_info: &Process,
+    open_files: &Result<Vec<OpenFile>>,
+    history: &History,
+) {
+    ui.heading(format!(
+        "{} - PID: {}",
+        proc_info.get_name(),
+        proc_info.get_pid()
+    ));
+    ui.label(proc_info.get_path());
+    ui.label(format!(
+        "Last CPU: {}  Allowed CPUs: {}",
+        proc_info.get_processor(),
+        format_cpu_list(proc_info.get_cpus_allowed())
+    ));
+    ui.separator();
+    process_history_chart(ui, history, proc_info.get_pid());
+    ui.separator();
+    match open_files {
+        Ok(open_files) => {
+            ui.label(format!("Open files: {}", open_files.len()));
+            open_files_layout(ui, open_files);
+        }
+        Err(err) => {
+            ui.label(format!("Could not read open files: {}", err));
+        }
+    }
+}
+
+fn open_files_layout(ui: &mut egui::Ui, open_files: &[OpenFile]) {
+    TableBuilder::new(ui)
+        .striped(true)
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::auto())
+        .column(Column::remainder().clip(true))
+        .header(20.0, |mut header| {
+            for title in ["FD", "Type", "Mode", "Pos", "Target"] {
+                header.col(|ui| {
+                    ui.strong(title);
+                });
+            }
+        })
+        .body(|body| {
+            body.rows(18.0, open_files.len(), |mut row| {
+                let open_file = &open_files[row.index()];
+                row.col(|ui| {
+                    ui.label(open_file.get_fd().to_string());
+                });
+                row.col(|ui| {
+                    ui.label(open_file.get_kind().as_str());
+                });
+                row.col(|ui| {
+                    ui.label(open_file.get_access_mode())
+                        .on_hover_text(format!("flags: 0{:o}", open_file.get_flags()));
+                });
+                row.col(|ui| {
+                    ui.label(open_file.get_position().to_string());
+                });
+                row.col(|ui| {
+                    ui.label(open_file.get_target());
+                });
+            });
+        });
+}
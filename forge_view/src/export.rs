@@ -0,0 +1,108 @@
+use anyhow::Result;
+use eframe::egui;
+use process::{export_csv, export_json, export_ndjson, ExportFormat, Filter, Process, ProcessTree};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+const EXPORT_FORMATS: [ExportFormat; 3] =
+    [ExportFormat::Json, ExportFormat::Csv, ExportFormat::Ndjson];
+
+pub struct ExportDialog {
+    format: ExportFormat,
+    path: String,
+}
+
+impl ExportDialog {
+    pub fn new() -> Self {
+        Self {
+            format: ExportFormat::Json,
+            path: "forge_view.json".to_string(),
+        }
+    }
+    /// Only the JSON tree carries the sockets of each process.
+    pub fn exports_sockets(&self) -> bool {
+        self.format == ExportFormat::Json
+    }
+}
+
+/// Exports what the views show: the filter applies to the tree and the list alike.
+pub fn export_window(
+    ctx: &egui::Context,
+    export_dialog: &mut Option<ExportDialog>,
+    proc_tree: &ProcessTree,
+    system_list: &[(u32, Process)],
+    filter: Option<&Filter>,
+    action_status: &mut Option<String>,
+) {
+    let Some(dialog) = export_dialog else {
+        return;
+    };
+    let mut open = true;
+    let mut exported = false;
+    egui::Window::new("Export Snapshot")
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            egui::Grid::new("Export Settings").show(ui, |ui| {
+                ui.label("Format:");
+                let previous_format = dialog.format;
+                egui::ComboBox::from_id_source("Export Format")
+                    .selected_text(dialog.format.as_str())
+                    .show_ui(ui, |ui| {
+                        for format in EXPORT_FORMATS {
+                            ui.selectable_value(&mut dialog.format, format, format.as_str());
+                        }
+                    });
+                if dialog.format != previous_format {
+                    dialog.path = PathBuf::from(&dialog.path)
+                        .with_extension(dialog.format.as_str())
+                        .to_string_lossy()
+                        .to_string();
+                }
+                ui.end_row();
+                ui.label("File:");
+                ui.text_edit_singleline(&mut dialog.path);
+                ui.end_row();
+            });
+            if ui.button("Export").clicked() {
+                let procs: Vec<&Process> = system_list
+                    .iter()
+                    .map(|(_, proc_info)| proc_info)
+                    .filter(|proc_info| filter.is_none_or(|filter| filter.matches(proc_info)))
+                    .collect();
+                *action_status = Some(
+                    match write_export(dialog.format, &dialog.path, proc_tree, &procs) {
+                        Ok(()) => {
+                            exported = true;
+                            format!(
+                                "Exported {} snapshot to {}",
+                                dialog.format.as_str(),
+                                dialog.path
+                            )
+                        }
+                        Err(err) => format!("Error: {}", err),
+                    },
+                );
+            }
+        });
+    if !open || exported {
+        *export_dialog = None;
+    }
+}
+
+fn write_export(
+    format: ExportFormat,
+    path: &str,
+    proc_tree: &ProcessTree,
+    procs: &[&Process],
+) -> Result<()> {
+    let writer = BufWriter::new(File::create(path)?);
+    match format {
+        ExportFormat::Json => export_json(proc_tree, writer),
+        ExportFormat::Csv => export_csv(procs.iter().copied(), writer),
+        ExportFormat::Ndjson => export_ndjson(procs.iter().copied(), SystemTime::now(), writer),
+    }
+}
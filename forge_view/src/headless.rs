@@ -0,0 +1,630 @@
+use crate::{format_bytes, F32_PRECISION};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use process::{
+    build_process_tree, export_csv, export_json, export_ndjson, filter_process_tree, ExportFormat,
+    Filter, Process, ProcessTreeNode, Recorder, SnapshotSource, System,
+};
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ProcColumn {
+    Pid,
+    Ppid,
+    Name,
+    User,
+    Cpu,
+    Mem,
+    Rss,
+    Threads,
+    Pri,
+    Ni,
+    ReadRate,
+    WriteRate,
+    Read,
+    Written,
+    Path,
+}
+
+impl ProcColumn {
+    pub fn header(&self) -> &'static str {
+        match self {
+            ProcColumn::Pid => "PID",
+            ProcColumn::Ppid => "PPID",
+            ProcColumn::Name => "NAME",
+            ProcColumn::User => "USER",
+            ProcColumn::Cpu => "%CPU",
+            ProcColumn::Mem => "%MEM",
+            ProcColumn::Rss => "RSS",
+            ProcColumn::Threads => "THR",
+            ProcColumn::Pri => "PRI",
+            ProcColumn::Ni => "NI",
+            ProcColumn::ReadRate => "READ/S",
+            ProcColumn::WriteRate => "WRITE/S",
+            ProcColumn::Read => "READ",
+            ProcColumn::Written => "WRITTEN",
+            ProcColumn::Path => "PATH",
+        }
+    }
+    pub fn is_text(&self) -> bool {
+        matches!(self, ProcColumn::Name | ProcColumn::User | ProcColumn::Path)
+    }
+    /// Text and ID columns read naturally in ascending order, metrics in descending.
+    pub fn is_descending_by_default(&self) -> bool {
+        !self.is_text() && !matches!(self, ProcColumn::Pid | ProcColumn::Ppid)
+    }
+    pub fn value(&self, proc_info: &Process) -> String {
+        let io = proc_info.get_io();
+        let scheduling = proc_info.get_scheduling();
+        match self {
+            ProcColumn::Pid => proc_info.get_pid().to_string(),
+            ProcColumn::Ppid => proc_info.get_ppid().to_string(),
+            ProcColumn::Name => proc_info.get_name().clone(),
+            ProcColumn::User => proc_info.get_user().clone(),
+            ProcColumn::Cpu => format!("{:.1$}", proc_info.get_cpu_used(), F32_PRECISION),
+            ProcColumn::Mem => format!("{:.1$}", proc_info.get_mem_used(), F32_PRECISION),
+            ProcColumn::Rss => format_bytes(proc_info.get_memory().get_rss()),
+            ProcColumn::Threads => proc_info.get_threads().len().to_string(),
+            ProcColumn::Pri => scheduling.get_priority().to_string(),
+            ProcColumn::Ni => scheduling.get_nice().to_string(),
+            ProcColumn::ReadRate => format_bytes(io.get_read_rate() as u64),
+            ProcColumn::WriteRate => format_bytes(io.get_write_rate() as u64),
+            ProcColumn::Read => format_bytes(io.get_read_bytes()),
+            ProcColumn::Written => format_bytes(io.get_write_bytes()),
+            ProcColumn::Path => proc_info.get_path().clone(),
+        }
+    }
+    pub fn compare(&self, proc_a: &Process, proc_b: &Process) -> Ordering {
+        let (io_a, io_b) = (proc_a.get_io(), proc_b.get_io());
+        let (scheduling_a, scheduling_b) = (proc_a.get_scheduling(), proc_b.get_scheduling());
+        match self {
+            ProcColumn::Pid => proc_a.get_pid().cmp(&proc_b.get_pid()),
+            ProcColumn::Ppid => proc_a.get_ppid().cmp(&proc_b.get_ppid()),
+            ProcColumn::Name => proc_a.get_name().cmp(proc_b.get_name()),
+            ProcColumn::User => proc_a.get_user().cmp(proc_b.get_user()),
+            ProcColumn::Cpu => proc_a.get_cpu_used().total_cmp(&proc_b.get_cpu_used()),
+            ProcColumn::Mem => proc_a.get_mem_used().total_cmp(&proc_b.get_mem_used()),
+            ProcColumn::Rss => proc_a
+                .get_memory()
+                .get_rss()
+                .cmp(&proc_b.get_memory().get_rss()),
+            ProcColumn::Threads => proc_a.get_threads().len().cmp(&proc_b.get_threads().len()),
+            ProcColumn::Pri => scheduling_a
+                .get_priority()
+                .cmp(&scheduling_b.get_priority()),
+            ProcColumn::Ni => scheduling_a.get_nice().cmp(&scheduling_b.get_nice()),
+            ProcColumn::ReadRate => io_a.get_read_rate().total_cmp(&io_b.get_read_rate()),
+            ProcColumn::WriteRate => io_a.get_write_rate().total_cmp(&io_b.get_write_rate()),
+            ProcColumn::Read => io_a.get_read_bytes().cmp(&io_b.get_read_bytes()),
+            ProcColumn::Written => io_a.get_write_bytes().cmp(&io_b.get_write_bytes()),
+            ProcColumn::Path => proc_a.get_path().cmp(proc_b.get_path()),
+        }
+    }
+    /// Ties are broken by PID, so rows keep their order between refreshes.
+    pub fn sort_order(&self, descending: bool, proc_a: &Process, proc_b: &Process) -> Ordering {
+        let ordering = self.compare(proc_a, proc_b);
+        match descending {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+        .then(proc_a.get_pid().cmp(&proc_b.get_pid()))
+    }
+}
+
+pub struct HeadlessOptions {
+    pub columns: Vec<ProcColumn>,
+    pub sort: ProcColumn,
+    pub descending: bool,
+    pub filter: Option<String>,
+    pub tree: bool,
+}
+
+impl HeadlessOptions {
+    pub fn compare(&self, proc_a: &Process, proc_b: &Process) -> Ordering {
+        self.sort.sort_order(self.descending, proc_a, proc_b)
+    }
+}
+
+/// A filter expression typed into a view.
+#[derive(Default)]
+pub struct FilterInput {
+    filter: Option<Filter>,
+    error: Option<String>,
+}
+
+impl FilterInput {
+    pub fn new(filter: Option<Filter>) -> Self {
+        Self {
+            filter,
+            error: None,
+        }
+    }
+    /// An empty text clears the filter.
+    pub fn set_text(&mut self, filter_text: &str) {
+        match filter_text.trim() {
+            "" => {
+                self.filter = None;
+                self.error = None;
+            }
+            filter_text => match Filter::parse(filter_text) {
+                Ok(filter) => {
+                    self.filter = Some(filter);
+                    self.error = None;
+                }
+                // Keep the last valid filter applied while the expression is being typed
+                Err(err) => self.error = Some(err.to_string()),
+            },
+        }
+    }
+    pub fn get_filter(&self) -> Option<&Filter> {
+        self.filter.as_ref()
+    }
+    pub fn get_error(&self) -> Option<&String> {
+        self.error.as_ref()
+    }
+}
+
+/// Prints a single snapshot to stdout. Read locally, CPU usage is averaged over each
+/// process lifetime, like `ps`.
+pub fn run_headless(mut source: SnapshotSource, options: &HeadlessOptions) -> Result<()> {
+    let filter = options.filter.as_deref().map(Filter::parse).transpose()?;
+    let mut system = source.next_snapshot()?;
+    let table = render_snapshot(&mut system, filter.as_ref(), options);
+    io::stdout().lock().write_all(table.as_bytes())?;
+    Ok(())
+}
+
+// The process tree or the sorted process list as a table
+fn render_snapshot(
+    system: &mut System,
+    filter: Option<&Filter>,
+    options: &HeadlessOptions,
+) -> String {
+    if options.tree {
+        let mut proc_tree = build_process_tree(system);
+        if let Some(filter) = filter {
+            proc_tree = filter_process_tree(&proc_tree, filter);
+        }
+        let mut rows: Vec<(usize, &Process)> = Vec::new();
+        tree_rows(&proc_tree.root, 0, options, &mut rows);
+        return render_table(&options.columns, &rows);
+    }
+    let proc_list = sorted_proc_list(system, filter, options);
+    let rows: Vec<(usize, &Process)> = proc_list
+        .iter()
+        .map(|(_, proc_info)| (0, proc_info))
+        .collect();
+    render_table(&options.columns, &rows)
+}
+
+pub struct BatchOptions {
+    pub iterations: Option<u32>,
+    pub top: Option<usize>,
+    pub interval: Duration,
+}
+
+/// Prints a summary line and the top processes every `interval`, like `top -b`,
+/// `iterations` times or until interrupted.
+pub fn run_batch(
+    mut source: SnapshotSource,
+    options: &HeadlessOptions,
+    batch_options: &BatchOptions,
+) -> Result<()> {
+    let filter = options.filter.as_deref().map(Filter::parse).transpose()?;
+    let mut stdout = io::stdout().lock();
+    let mut iteration = 0u32;
+    while batch_options
+        .iterations
+        .is_none_or(|iterations| iteration < iterations)
+    {
+        if iteration > 0 {
+            thread::sleep(batch_options.interval);
+            writeln!(stdout)?;
+        }
+        let mut system = source.next_snapshot()?;
+        let iteration_output = render_batch_iteration(
+            &mut system,
+            filter.as_ref(),
+            options,
+            batch_options.top,
+            SystemTime::now(),
+        );
+        stdout.write_all(iteration_output.as_bytes())?;
+        stdout.flush()?;
+        iteration += 1;
+    }
+    Ok(())
+}
+
+// The summary line, counting every matching process, followed by the `top` first of them
+fn render_batch_iteration(
+    system: &mut System,
+    filter: Option<&Filter>,
+    options: &HeadlessOptions,
+    top: Option<usize>,
+    time: SystemTime,
+) -> String {
+    let mem_info = system.get_mem_info();
+    let summary = format!(
+        "{}  CPU: %{:.5$}  Memory: %{:.5$}  Used: {} / {}",
+        format_utc(time),
+        system.get_total_cpu_usage(),
+        system.get_total_mem_usage(),
+        format_bytes(mem_info.get_used()),
+        format_bytes(mem_info.get_total()),
+        F32_PRECISION
+    );
+    let mut proc_list = sorted_proc_list(system, filter, options);
+    let summary = format!("{}  Processes: {}\n", summary, proc_list.len());
+    if let Some(top) = top {
+        proc_list.truncate(top);
+    }
+    let rows: Vec<(usize, &Process)> = proc_list
+        .iter()
+        .map(|(_, proc_info)| (0, proc_info))
+        .collect();
+    summary + &render_table(&options.columns, &rows)
+}
+
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub output: Option<PathBuf>,
+    pub samples: u32,
+    pub interval: Duration,
+}
+
+/// Writes the JSON tree or the CSV list once, or `samples` NDJSON samples `interval` apart,
+/// to `output` or stdout.
+pub fn run_export(
+    mut source: SnapshotSource,
+    options: &HeadlessOptions,
+    export_options: &ExportOptions,
+) -> Result<()> {
+    let filter = options.filter.as_deref().map(Filter::parse).transpose()?;
+    if export_options.samples > 1 && export_options.format != ExportFormat::Ndjson {
+        return Err(anyhow!("Multiple samples can only be exported as ndjson"));
+    }
+    source.set_read_sockets(export_options.format == ExportFormat::Json);
+    let mut writer: Box<dyn Write> = match &export_options.output {
+        Some(output) => Box::new(BufWriter::new(File::create(output)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    for sample in 0..export_options.samples.max(1) {
+        if sample > 0 {
+            thread::sleep(export_options.interval);
+        }
+        let mut system = source.next_snapshot()?;
+        match export_options.format {
+            ExportFormat::Json => {
+                let mut proc_tree = build_process_tree(&system);
+                if let Some(filter) = &filter {
+                    proc_tree = filter_process_tree(&proc_tree, filter);
+                }
+                export_json(&proc_tree, &mut writer)?;
+                writeln!(writer)?;
+            }
+            ExportFormat::Csv => {
+                let proc_list = sorted_proc_list(&mut system, filter.as_ref(), options);
+                export_csv(
+                    proc_list.iter().map(|(_, proc_info)| proc_info),
+                    &mut writer,
+                )?;
+            }
+            ExportFormat::Ndjson => {
+                let proc_list = sorted_proc_list(&mut system, filter.as_ref(), options);
+                export_ndjson(
+                    proc_list.iter().map(|(_, proc_info)| proc_info),
+                    SystemTime::now(),
+                    &mut writer,
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Appends `samples` snapshots `interval` apart to the recording at `path`, or keeps
+/// recording until interrupted.
+pub fn run_record(
+    mut source: SnapshotSource,
+    path: &Path,
+    samples: Option<u32>,
+    interval: Duration,
+) -> Result<()> {
+    let mut recorder = Recorder::open(path)?;
+    // Replays show connections like a live view
+    source.set_read_sockets(true);
+    let mut sample = 0u32;
+    while samples.is_none_or(|samples| sample < samples) {
+        if sample > 0 {
+            thread::sleep(interval);
+        }
+        let system = source.next_snapshot()?;
+        recorder.record(&system)?;
+        sample += 1;
+    }
+    Ok(())
+}
+
+fn sorted_proc_list(
+    system: &mut System,
+    filter: Option<&Filter>,
+    options: &HeadlessOptions,
+) -> Vec<(u32, Process)> {
+    let mut proc_list = system.get_procs_as_list();
+    if let Some(filter) = filter {
+        proc_list.retain(|(_, proc_info)| filter.matches(proc_info));
+    }
+    proc_list.sort_by(|(_, proc_a), (_, proc_b)| options.compare(proc_a, proc_b));
+    proc_list
+}
+
+// Children are sorted among their siblings; the placeholder root itself is not printed
+pub fn tree_rows<'a>(
+    proc_node: &'a ProcessTreeNode,
+    depth: usize,
+    options: &HeadlessOptions,
+    rows: &mut Vec<(usize, &'a Process)>,
+) {
+    let mut children: Vec<&ProcessTreeNode> = proc_node.children.iter().collect();
+    children.sort_by(|child_a, child_b| options.compare(&child_a.proc_info, &child_b.proc_info));
+    for child in children {
+        rows.push((depth, &child.proc_info));
+        tree_rows(child, depth + 1, options, rows);
+    }
+}
+
+// Text columns are aligned left and the others right, names are indented by their depth
+fn render_table(columns: &[ProcColumn], rows: &[(usize, &Process)]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(depth, proc_info)| {
+            columns
+                .iter()
+                .map(|column| match column {
+                    ProcColumn::Name => {
+                        format!("{}{}", "  ".repeat(*depth), column.value(proc_info))
+                    }
+                    _ => column.value(proc_info),
+                })
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            cells
+                .iter()
+                .map(|row| row[index].chars().count())
+                .fold(column.header().len(), usize::max)
+        })
+        .collect();
+    let format_row = |values: Vec<&str>| -> String {
+        let mut line = values
+            .iter()
+            .zip(columns.iter().zip(widths.iter()))
+            .map(|(value, (column, width))| match column.is_text() {
+                true => format!("{:<1$}", value, width),
+                false => format!("{:>1$}", value, width),
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        line.truncate(line.trim_end().len());
+        line.push('\n');
+        line
+    };
+    let mut table = format_row(columns.iter().map(|column| column.header()).collect());
+    for row in cells.iter() {
+        table.push_str(&format_row(row.iter().map(String::as_str).collect()));
+    }
+    table
+}
+
+// RFC 3339 in UTC, converting days since the epoch with Howard Hinnant's civil_from_days
+fn format_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, day_secs) = ((secs / 86400) as i64, secs % 86400);
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = match shifted_month < 10 {
+        true => shifted_month + 3,
+        false => shifted_month - 9,
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use process::fixture::{Fixture, FixtureProc};
+
+    // init with the children beta and alpha and a grandchild also named beta, with ties in
+    // name, nice and threads
+    fn fixture_system() -> System {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        for (pid, ppid, name, nice, threads) in [
+            (1, 0, "init", 0, 1),
+            (2, 1, "beta", 5, 3),
+            (3, 1, "alpha", 5, 1),
+            (4, 2, "beta", -5, 3),
+        ] {
+            let mut proc = FixtureProc::new(pid, ppid);
+            proc.name = String::from(name);
+            proc.nice = nice;
+            proc.threads = threads;
+            fixture.write_proc(&proc).unwrap();
+        }
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        system
+    }
+
+    fn sorted_pids(column: ProcColumn, descending: bool) -> Vec<u32> {
+        let mut proc_list = fixture_system().get_procs_as_list();
+        proc_list.sort_by(|(_, proc_a), (_, proc_b)| column.sort_order(descending, proc_a, proc_b));
+        proc_list.iter().map(|(pid, _)| *pid).collect()
+    }
+
+    #[test]
+    fn columns_sort_in_both_directions() {
+        assert_eq!(sorted_pids(ProcColumn::Pid, false), vec![1, 2, 3, 4]);
+        assert_eq!(sorted_pids(ProcColumn::Pid, true), vec![4, 3, 2, 1]);
+        assert_eq!(sorted_pids(ProcColumn::Ppid, false), vec![1, 2, 3, 4]);
+        assert_eq!(sorted_pids(ProcColumn::Name, false), vec![3, 2, 4, 1]);
+        assert_eq!(sorted_pids(ProcColumn::Ni, false), vec![4, 1, 2, 3]);
+        assert_eq!(sorted_pids(ProcColumn::Pri, false), vec![4, 1, 2, 3]);
+    }
+
+    #[test]
+    fn only_metrics_sort_descending_by_default() {
+        assert!(!ProcColumn::Pid.is_descending_by_default());
+        assert!(!ProcColumn::Name.is_descending_by_default());
+        assert!(!ProcColumn::Path.is_descending_by_default());
+        assert!(!ProcColumn::Ppid.is_descending_by_default());
+        assert!(ProcColumn::Cpu.is_descending_by_default());
+    }
+
+    #[test]
+    fn ties_are_broken_by_ascending_pid() {
+        // Also when the column itself is sorted in descending order
+        assert_eq!(sorted_pids(ProcColumn::Name, true), vec![1, 2, 4, 3]);
+        assert_eq!(sorted_pids(ProcColumn::Ni, true), vec![2, 3, 1, 4]);
+        assert_eq!(sorted_pids(ProcColumn::Threads, true), vec![2, 4, 1, 3]);
+        assert_eq!(sorted_pids(ProcColumn::Ppid, true), vec![4, 2, 3, 1]);
+        assert_eq!(sorted_pids(ProcColumn::User, false), vec![1, 2, 3, 4]);
+    }
+
+    fn headless_options(
+        columns: Vec<ProcColumn>,
+        sort: ProcColumn,
+        descending: bool,
+    ) -> HeadlessOptions {
+        HeadlessOptions {
+            columns,
+            sort,
+            descending,
+            filter: None,
+            tree: false,
+        }
+    }
+
+    #[test]
+    fn tables_only_show_the_selected_columns() {
+        let options = headless_options(
+            vec![ProcColumn::Pid, ProcColumn::Name],
+            ProcColumn::Pid,
+            false,
+        );
+        assert_eq!(
+            render_snapshot(&mut fixture_system(), None, &options),
+            "PID NAME\n  1 init\n  2 beta\n  3 alpha\n  4 beta\n"
+        );
+        let options = headless_options(vec![ProcColumn::Ni, ProcColumn::Pid], ProcColumn::Ni, true);
+        assert_eq!(
+            render_snapshot(&mut fixture_system(), None, &options),
+            "NI PID\n 5   2\n 5   3\n 0   1\n-5   4\n"
+        );
+    }
+
+    #[test]
+    fn batch_prints_the_top_processes_after_counting_all() {
+        let options = headless_options(
+            vec![ProcColumn::Pid, ProcColumn::Threads],
+            ProcColumn::Threads,
+            true,
+        );
+        let output =
+            render_batch_iteration(&mut fixture_system(), None, &options, Some(2), UNIX_EPOCH);
+        let lines: Vec<&str> = output.lines().collect();
+        assert!(lines[0].starts_with("1970-01-01T00:00:00Z  CPU: %"));
+        assert!(lines[0].ends_with("  Processes: 4"));
+        assert_eq!(lines[1..], ["PID THR", "  2   3", "  4   3"]);
+        let filter = Filter::parse("name == \"beta\"").unwrap();
+        let output = render_batch_iteration(
+            &mut fixture_system(),
+            Some(&filter),
+            &options,
+            Some(5),
+            UNIX_EPOCH,
+        );
+        assert!(output.lines().next().unwrap().ends_with("  Processes: 2"));
+        assert_eq!(output.lines().count(), 4);
+    }
+
+    #[test]
+    fn trees_indent_names_by_depth() {
+        let mut options = headless_options(
+            vec![ProcColumn::Pid, ProcColumn::Name],
+            ProcColumn::Pid,
+            false,
+        );
+        options.tree = true;
+        assert_eq!(
+            render_snapshot(&mut fixture_system(), None, &options),
+            "PID NAME\n  1 init\n  2   beta\n  4     beta\n  3   alpha\n"
+        );
+        // Matches keep their ancestors, siblings are sorted
+        let filter = Filter::parse("pid == 4 || pid == 3").unwrap();
+        options.descending = true;
+        assert_eq!(
+            render_snapshot(&mut fixture_system(), Some(&filter), &options),
+            "PID NAME\n  1 init\n  3   alpha\n  2   beta\n  4     beta\n"
+        );
+    }
+
+    fn format_secs(secs: u64) -> String {
+        format_utc(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn utc_starts_at_the_epoch() {
+        assert_eq!(format_secs(0), "1970-01-01T00:00:00Z");
+        // Times before the epoch are clamped to it
+        assert_eq!(
+            format_utc(UNIX_EPOCH - Duration::from_secs(1)),
+            "1970-01-01T00:00:00Z"
+        );
+    }
+
+    #[test]
+    fn utc_counts_leap_days() {
+        assert_eq!(format_secs(1709210096), "2024-02-29T12:34:56Z");
+        assert_eq!(format_secs(1709210096 + 86400), "2024-03-01T12:34:56Z");
+        // 2000 is a leap year as a multiple of 400, 2100 is not
+        assert_eq!(format_secs(951782400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_secs(4107542400 - 86400), "2100-02-28T00:00:00Z");
+        assert_eq!(format_secs(4107542400), "2100-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn utc_rolls_over_the_year() {
+        assert_eq!(format_secs(946684799), "1999-12-31T23:59:59Z");
+        assert_eq!(format_secs(946684800), "2000-01-01T00:00:00Z");
+    }
+}
@@ -0,0 +1,185 @@
+use crate::F32_PRECISION;
+use anyhow::{anyhow, Result};
+use eframe::egui;
+use process::control::{self, Signal};
+use process::{
+    AgentClient, History, IoPriority, OpenFile, Sampler, SchedulingPolicy, SnapshotSource, System,
+};
+use std::fs;
+use std::time::Duration;
+
+pub fn local_host_name() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .map(|host_name| host_name.trim().to_string())
+        .ok()
+        .filter(|host_name| !host_name.is_empty())
+        .unwrap_or_else(|| String::from("localhost"))
+}
+
+/// How the processes of a host are inspected and acted on.
+pub enum HostControl {
+    /// From this process, the host is this machine read through its live `/proc`.
+    Direct,
+    /// Through a connection to the agent reading the host, besides the one of the sampler.
+    Agent(AgentClient),
+    /// Not at all, PIDs of a `--root` other than `/` may belong to unrelated processes.
+    Captured,
+}
+
+impl HostControl {
+    pub fn can_control(&self) -> bool {
+        !matches!(self, HostControl::Captured)
+    }
+    pub fn get_proc_open_files(&mut self, system: &System, pid: u32) -> Result<Vec<OpenFile>> {
+        match self {
+            HostControl::Agent(client) => client.get_proc_open_files(pid),
+            _ => system.get_proc_open_files(pid),
+        }
+    }
+    pub fn get_io_priority(&mut self, pid: u32) -> Result<IoPriority> {
+        match self {
+            HostControl::Direct => control::get_io_priority(pid),
+            HostControl::Agent(client) => client.get_io_priority(pid),
+            HostControl::Captured => Err(captured_error()),
+        }
+    }
+    pub fn send_signal(&mut self, pid: u32, signal: Signal) -> Result<()> {
+        match self {
+            HostControl::Direct => control::send_signal(pid, signal),
+            HostControl::Agent(client) => client.send_signal(pid, signal),
+            HostControl::Captured => Err(captured_error()),
+        }
+    }
+    pub fn set_nice(&mut self, pid: u32, nice: i32) -> Result<()> {
+        match self {
+            HostControl::Direct => control::set_nice(pid, nice),
+            HostControl::Agent(client) => client.set_nice(pid, nice),
+            HostControl::Captured => Err(captured_error()),
+        }
+    }
+    pub fn set_io_priority(&mut self, pid: u32, io_priority: IoPriority) -> Result<()> {
+        match self {
+            HostControl::Direct => control::set_io_priority(pid, io_priority),
+            HostControl::Agent(client) => client.set_io_priority(pid, io_priority),
+            HostControl::Captured => Err(captured_error()),
+        }
+    }
+    pub fn set_scheduling_policy(
+        &mut self,
+        pid: u32,
+        policy: SchedulingPolicy,
+        rt_priority: u32,
+    ) -> Result<()> {
+        match self {
+            HostControl::Direct => control::set_scheduling_policy(pid, policy, rt_priority),
+            HostControl::Agent(client) => client.set_scheduling_policy(pid, policy, rt_priority),
+            HostControl::Captured => Err(captured_error()),
+        }
+    }
+    pub fn set_cpu_affinity(&mut self, pid: u32, cpus: &[usize]) -> Result<()> {
+        match self {
+            HostControl::Direct => control::set_cpu_affinity(pid, cpus),
+            HostControl::Agent(client) => client.set_cpu_affinity(pid, cpus),
+            HostControl::Captured => Err(captured_error()),
+        }
+    }
+}
+
+fn captured_error() -> anyhow::Error {
+    anyhow!("Processes read from a captured root can not be controlled")
+}
+
+/// A machine to monitor, where its snapshots come from and how its processes are
+/// controlled.
+pub struct HostSource {
+    pub name: String,
+    pub control: HostControl,
+    pub source: SnapshotSource,
+}
+
+pub struct Host {
+    name: String,
+    control: HostControl,
+    sampler: Sampler,
+    latest: System,
+}
+
+impl Host {
+    pub fn spawn(
+        host: HostSource,
+        system: System,
+        interval: Duration,
+        ctx: &egui::Context,
+    ) -> Self {
+        let ctx = ctx.clone();
+        Self {
+            name: host.name,
+            control: host.control,
+            sampler: Sampler::spawn(host.source, interval, move || ctx.request_repaint()),
+            latest: system,
+        }
+    }
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+    pub fn can_control(&self) -> bool {
+        self.control.can_control()
+    }
+    pub fn get_control(&mut self) -> &mut HostControl {
+        &mut self.control
+    }
+    pub fn get_history(&self) -> &History {
+        self.sampler.get_history()
+    }
+    pub fn get_latest(&self) -> &System {
+        &self.latest
+    }
+    pub fn get_sampler(&mut self) -> &mut Sampler {
+        &mut self.sampler
+    }
+}
+
+/// Keeps the latest snapshot of every host and returns the new one of the active host.
+pub fn poll_hosts(hosts: &mut [Host], active_host: usize) -> Option<System> {
+    let mut active_snapshot: Option<System> = None;
+    for (index, host) in hosts.iter_mut().enumerate() {
+        if let Some(system) = host.sampler.try_recv_latest() {
+            if index == active_host {
+                active_snapshot = Some(system.clone());
+            }
+            host.latest = system;
+        }
+    }
+    active_snapshot
+}
+
+/// Lists the hosts when there are several. Returns the latest snapshot of the host
+/// switched to.
+pub fn hosts_panel(ctx: &egui::Context, hosts: &[Host], active_host: &mut usize) -> Option<System> {
+    if hosts.len() < 2 {
+        return None;
+    }
+    let mut switched_to: Option<usize> = None;
+    egui::SidePanel::left("Hosts")
+        .resizable(true)
+        .show(ctx, |ui| {
+            ui.heading("Hosts");
+            for (index, host) in hosts.iter().enumerate() {
+                let label = format!(
+                    "{}\nCPU %{:.3$}  Mem %{:.3$}",
+                    host.name,
+                    host.latest.get_total_cpu_usage(),
+                    host.latest.get_total_mem_usage(),
+                    F32_PRECISION
+                );
+                if ui.selectable_label(index == *active_host, label).clicked()
+                    && index != *active_host
+                {
+                    switched_to = Some(index);
+                }
+            }
+        });
+    let index = switched_to?;
+    *active_host = index;
+    Some(hosts[index].latest.clone())
+}
@@ -1,21 +1,310 @@
-use clap::Parser;
+mod affinity;
+mod charts;
+mod connections;
+mod details;
+mod export;
+mod headless;
+mod hosts;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod priority;
+mod replay;
+mod signals;
+mod tui;
+
+use affinity::{affinity_window, AffinityDialog};
+use anyhow::{anyhow, Result};
+use charts::system_history_chart;
+use clap::{Parser, Subcommand};
+use connections::connections_layout;
+use details::details_layout;
 use eframe::egui::{self, Vec2, Visuals};
 use egui_extras::{Column, TableBuilder};
-use process::{build_process_tree, Process, ProcessTree, ProcessTreeNode, System};
+use export::{export_window, ExportDialog};
+use headless::{
+    run_batch, run_export, run_headless, run_record, BatchOptions, ExportOptions, FilterInput,
+    HeadlessOptions, ProcColumn,
+};
+use hosts::{hosts_panel, local_host_name, poll_hosts, Host, HostControl, HostSource};
+use priority::{priority_window, PriorityDialog};
+use process::{
+    build_process_tree, default_agent_socket, filter_process_tree, run_agent, AgentClient,
+    CpuCoreUsage, ExportFormat, Filter, History, OpenFile, Process, ProcessTree, ProcessTreeNode,
+    SnapshotSource, System, TcpListen,
+};
+use replay::{replay_panel, Replay};
+use signals::{confirm_signal_window, handle_signal_request, signal_menu, SignalRequest};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tui::run_tui;
 
 #[derive(Parser)]
 #[command(version, about = "Forge View launch commands")]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
     #[arg(short, long, default_value = None)]
     width: Option<f32>,
     #[arg(short, long, default_value = None)]
     lheigth: Option<f32>,
+    #[arg(short, long, global = true, default_value = None)]
+    root: Option<PathBuf>,
+    /// Seconds of metric history kept for the charts
+    #[arg(long, global = true, default_value_t = 300)]
+    history: u64,
+    /// Seconds between background refreshes
+    #[arg(
+        short,
+        long,
+        short_alias = 'd',
+        global = true,
+        default_value_t = DEFAULT_INTERVAL_SECS
+    )]
+    interval: f32,
+    /// Take snapshots from a running agent instead of reading /proc, listening on the
+    /// default socket when no path is given
+    #[arg(long, num_args = 0..=1, conflicts_with_all = ["root", "replay"])]
+    attach: Option<Option<PathBuf>>,
+    /// Also monitor the agent listening on this TCP address, e.g. myhost:7878. Can be
+    /// repeated to list several hosts in the window
+    #[arg(long, conflicts_with = "replay")]
+    remote: Vec<String>,
+    /// Shared token authenticating TCP connections between agents and viewers. Prefer
+    /// the environment variable, command lines are visible to other users
+    #[arg(long, global = true, env = "FORGE_VIEW_TOKEN", hide_env_values = true)]
+    token: Option<String>,
+    /// Print a snapshot to stdout instead of opening a window
+    #[arg(long)]
+    headless: bool,
+    /// Show the process list and tree in the terminal instead of opening a window
+    #[arg(long, conflicts_with_all = ["headless", "replay"])]
+    tui: bool,
+    /// Columns shown in headless and terminal UI modes
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [
+            ProcColumn::Pid,
+            ProcColumn::User,
+            ProcColumn::Pri,
+            ProcColumn::Ni,
+            ProcColumn::Cpu,
+            ProcColumn::Mem,
+            ProcColumn::Rss,
+            ProcColumn::Name,
+        ]
+    )]
+    columns: Vec<ProcColumn>,
+    /// Column to sort by in headless and terminal UI modes
+    #[arg(long, value_enum, default_value_t = ProcColumn::Pid)]
+    sort: ProcColumn,
+    /// Sort in descending order in headless and terminal UI modes
+    #[arg(long)]
+    descending: bool,
+    /// Filter expression, e.g. `user == "postgres" && cpu > 5`
+    #[arg(long)]
+    filter: Option<String>,
+    /// Show the process tree instead of a flat list in headless and terminal UI modes
+    #[arg(long)]
+    tree: bool,
+    /// Print the top processes every --interval, like `top -b`, instead of opening a window
+    #[arg(short, long, conflicts_with_all = ["tui", "export", "record", "replay", "tree"])]
+    batch: bool,
+    /// Number of processes printed per iteration in batch mode, all when not set
+    #[arg(long)]
+    top: Option<usize>,
+    /// Column ranking the processes in batch mode, metrics list the largest first and text
+    /// and PID/PPID columns in ascending order
+    #[arg(long, value_enum, default_value_t = ProcColumn::Cpu)]
+    by: ProcColumn,
+    /// Write a snapshot as json (tree), csv or ndjson instead of opening a window
+    #[arg(long)]
+    export: Option<ExportFormat>,
+    /// File written by --export, stdout when not set
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Number of samples written by --export (ndjson only), --record or --batch, taken
+    /// --interval apart. --export writes one, --record and --batch run until interrupted
+    /// when not set
+    #[arg(short = 'n', long)]
+    samples: Option<u32>,
+    /// Append snapshots to a recording file instead of opening a window
+    #[arg(long)]
+    record: Option<PathBuf>,
+    /// Open a recording file and browse it instead of live metrics
+    #[arg(long, conflicts_with = "record")]
+    replay: Option<PathBuf>,
+    /// Serve OpenMetrics on this address, e.g. 127.0.0.1:9898, instead of opening a window
+    #[cfg(feature = "metrics")]
+    #[arg(long)]
+    metrics_listen: Option<String>,
+    /// Filter expression selecting the processes exported as per-process series
+    #[cfg(feature = "metrics")]
+    #[arg(long)]
+    metrics_allow: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Sample processes in the background and serve them to viewers started with --attach
+    Agent {
+        /// Unix socket to listen on, $XDG_RUNTIME_DIR/forge_view.sock by default
+        #[arg(long)]
+        socket: Option<PathBuf>,
+        /// Also accept viewers over TCP on this address, e.g. 0.0.0.0:7878, checking --token
+        #[arg(long)]
+        listen: Option<String>,
+        /// Let TCP viewers signal and reprioritize processes, which they may not by default
+        #[arg(long, requires = "listen")]
+        allow_control: bool,
+    },
 }
 
 const F32_PRECISION: usize = 2;
+const DEFAULT_INTERVAL_SECS: f32 = 1f32;
 
 fn main() -> Result<(), eframe::Error> {
     let args = Args::parse();
+    let mut interval = args.interval;
+    if !interval.is_finite() || interval <= 0f32 {
+        println!("Error: Refresh interval must be greater than 0!");
+        println!("Using default refresh interval");
+        interval = DEFAULT_INTERVAL_SECS;
+    }
+    if let Some(Command::Agent {
+        socket,
+        listen,
+        allow_control,
+    }) = args.command
+    {
+        let mut system = new_system(args.root);
+        system.set_history_duration(Duration::from_secs(args.history));
+        let socket = socket.unwrap_or_else(default_agent_socket);
+        let tcp = match (listen, args.token) {
+            (Some(address), Some(token)) if !token.is_empty() => Some(TcpListen {
+                address,
+                token,
+                allow_control,
+            }),
+            (Some(_), _) => {
+                eprintln!("Error: Listening on TCP needs a --token");
+                std::process::exit(1);
+            }
+            (None, _) => None,
+        };
+        if let Err(err) = run_agent(system, socket, tcp, Duration::from_secs_f32(interval)) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let hosts = match new_hosts(
+        args.root,
+        Duration::from_secs(args.history),
+        args.attach,
+        args.remote,
+        args.token,
+    ) {
+        Ok(hosts) => hosts,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    #[cfg(feature = "metrics")]
+    if let Some(listen) = &args.metrics_listen {
+        let source = match single_source(hosts) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        };
+        if let Err(err) = metrics::run_metrics_server(
+            source,
+            listen,
+            args.metrics_allow.as_deref(),
+            Duration::from_secs_f32(interval),
+        ) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.headless || args.tui || args.batch || args.export.is_some() || args.record.is_some() {
+        let source = match single_source(hosts) {
+            Ok(source) => source,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        };
+        let options = HeadlessOptions {
+            columns: args.columns,
+            sort: args.sort,
+            descending: args.descending,
+            filter: args.filter,
+            tree: args.tree,
+        };
+        let result = match (args.record, args.export) {
+            (Some(record), _) => run_record(
+                source,
+                &record,
+                args.samples,
+                Duration::from_secs_f32(interval),
+            ),
+            (None, Some(format)) => run_export(
+                source,
+                &options,
+                &ExportOptions {
+                    format,
+                    output: args.output,
+                    samples: args.samples.unwrap_or(1),
+                    interval: Duration::from_secs_f32(interval),
+                },
+            ),
+            (None, None) if args.batch => run_batch(
+                source,
+                &HeadlessOptions {
+                    sort: args.by,
+                    descending: args.by.is_descending_by_default(),
+                    ..options
+                },
+                &BatchOptions {
+                    iterations: args.samples,
+                    top: args.top,
+                    interval: Duration::from_secs_f32(interval),
+                },
+            ),
+            (None, None) if args.tui => run_tui(source, options, Duration::from_secs_f32(interval)),
+            (None, None) => run_headless(source, &options),
+        };
+        match result {
+            Ok(_) => return Ok(()),
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    let startup = match args.replay {
+        Some(replay) => Replay::open(replay, Duration::from_secs(args.history))
+            .map(|(replay, system)| Startup::Replay(replay, Box::new(system))),
+        None => hosts
+            .into_iter()
+            .map(|mut host| host.source.next_snapshot().map(|system| (host, system)))
+            .collect::<Result<Vec<(HostSource, System)>>>()
+            .map(Startup::Live),
+    };
+    let startup = match startup {
+        Ok(startup) => startup,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
     let mut native_options = eframe::NativeOptions::default();
     if let (Some(width), Some(heigth)) = (args.width, args.lheigth) {
         if width == 0f32 || heigth == 0f32 {
@@ -28,45 +317,228 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "Forge View",
         native_options,
-        Box::new(|cc| Box::new(ForgeViewApp::new(cc))),
+        Box::new(move |cc| {
+            Box::new(ForgeViewApp::new(
+                cc,
+                Duration::from_secs_f32(interval),
+                startup,
+            ))
+        }),
     )
 }
+fn new_system(root: Option<PathBuf>) -> System {
+    match root {
+        Some(root) => System::with_root(root),
+        None => System::new(),
+    }
+}
+
+// This machine, read from /proc below `root` or through the agent at `attach`,
+// followed by the agents at `remotes`. Processes of agents are controlled through a
+// second connection, so actions do not wait for a snapshot. PIDs read below another
+// root than / belong to a capture, so they can not be controlled
+fn new_hosts(
+    root: Option<PathBuf>,
+    history_duration: Duration,
+    attach: Option<Option<PathBuf>>,
+    remotes: Vec<String>,
+    token: Option<String>,
+) -> Result<Vec<HostSource>> {
+    let (source, control) = match attach {
+        Some(socket) => {
+            let socket = socket.unwrap_or_else(default_agent_socket);
+            let control = HostControl::Agent(AgentClient::connect(&socket)?);
+            (
+                SnapshotSource::Agent(AgentClient::connect(socket)?),
+                control,
+            )
+        }
+        None => {
+            let control = match root.as_deref().is_none_or(|root| root == Path::new("/")) {
+                true => HostControl::Direct,
+                false => HostControl::Captured,
+            };
+            let mut system = new_system(root);
+            system.set_history_duration(history_duration);
+            (system.into(), control)
+        }
+    };
+    let mut hosts = vec![HostSource {
+        name: local_host_name(),
+        control,
+        source,
+    }];
+    for remote in remotes {
+        let token = token
+            .as_deref()
+            .ok_or_else(|| anyhow!("Connecting to {} needs a --token", remote))?;
+        let control = HostControl::Agent(AgentClient::connect_tcp(&remote, token)?);
+        let source = SnapshotSource::Agent(AgentClient::connect_tcp(&remote, token)?);
+        hosts.push(HostSource {
+            name: remote,
+            control,
+            source,
+        });
+    }
+    Ok(hosts)
+}
+
+// Modes other than the window show a single host, the remote one when given
+fn single_source(mut hosts: Vec<HostSource>) -> Result<SnapshotSource> {
+    if hosts.len() > 2 {
+        return Err(anyhow!(
+            "Several remote hosts can only be shown in the window"
+        ));
+    }
+    hosts
+        .pop()
+        .map(|host| host.source)
+        .ok_or_else(|| anyhow!("No host to show"))
+}
+
+// What the window shows first, with where the following snapshots come from
+enum Startup {
+    Live(Vec<(HostSource, System)>),
+    Replay(Replay, Box<System>),
+}
+
+#[derive(PartialEq)]
 enum AppStates {
     ProcList,
     ProcTree,
+    Connections,
 }
 
 struct ForgeViewApp {
     metric_state: AppStates,
     system_metric: System,
+    hosts: Vec<Host>,
+    active_host: usize,
+    replay: Option<Replay>,
     system_list: Vec<(u32, Process)>,
     system_tree: ProcessTree,
+    filtered_tree: Option<ProcessTree>,
+    filter_text: String,
+    filter: FilterInput,
+    expanded_procs: HashSet<u32>,
+    sort_column: ProcColumn,
+    sort_descending: bool,
+    show_unix_sockets: bool,
+    selected_pid: Option<u32>,
+    selected_open_files: Result<Vec<OpenFile>>,
+    pending_signal: Option<SignalRequest>,
+    action_status: Option<String>,
+    priority_dialog: Option<PriorityDialog>,
+    affinity_dialog: Option<AffinityDialog>,
+    export_dialog: Option<ExportDialog>,
     dark_mode: bool,
 }
 
-impl Default for ForgeViewApp {
-    fn default() -> Self {
-        let mut system = System::new();
-        match system.refresh_system_info() {
-            Ok(_) => {}
-            Err(_) => println!("Error: Process lib could not compute metrics!"),
+impl ForgeViewApp {
+    fn new(cc: &eframe::CreationContext<'_>, interval: Duration, startup: Startup) -> Self {
+        cc.egui_ctx.set_fonts(egui::FontDefinitions::default());
+        match startup {
+            Startup::Replay(replay, system) => Self::with_system(*system, Vec::new(), Some(replay)),
+            Startup::Live(hosts) => {
+                let hosts: Vec<Host> = hosts
+                    .into_iter()
+                    .map(|(host, system)| Host::spawn(host, system, interval, &cc.egui_ctx))
+                    .collect();
+                let system = hosts
+                    .first()
+                    .map(|host| host.get_latest().clone())
+                    .unwrap_or_default();
+                Self::with_system(system, hosts, None)
+            }
         }
+    }
+    fn with_system(mut system: System, hosts: Vec<Host>, replay: Option<Replay>) -> Self {
         let process_tree = process::build_process_tree(&system);
         let sys_vector = system.get_procs_as_list();
-        Self {
+        let mut app = Self {
             dark_mode: true,
             metric_state: AppStates::ProcList,
             system_metric: system,
+            hosts,
+            active_host: 0,
+            replay,
             system_list: sys_vector,
             system_tree: process_tree,
+            filtered_tree: None,
+            filter_text: String::new(),
+            filter: FilterInput::default(),
+            expanded_procs: HashSet::new(),
+            sort_column: ProcColumn::Pid,
+            sort_descending: false,
+            show_unix_sockets: false,
+            selected_pid: None,
+            selected_open_files: Ok(Vec::new()),
+            pending_signal: None,
+            action_status: None,
+            priority_dialog: None,
+            affinity_dialog: None,
+            export_dialog: None,
+        };
+        app.sort_proc_list();
+        app
+    }
+    fn apply_snapshot(&mut self, system: System) {
+        self.system_metric = system;
+        self.system_tree = build_process_tree(&self.system_metric);
+        self.filter_tree();
+        self.system_list = self.system_metric.get_procs_as_list();
+        self.sort_proc_list();
+    }
+    fn set_filter(&mut self) {
+        self.filter.set_text(&self.filter_text);
+        self.filter_tree();
+    }
+    fn filter_tree(&mut self) {
+        self.filtered_tree = self
+            .filter
+            .get_filter()
+            .map(|filter| filter_process_tree(&self.system_tree, filter));
+    }
+    // Open files are read on selection only, as an agent answers over the network
+    fn select_proc(&mut self, pid: u32) {
+        self.selected_pid = Some(pid);
+        let control = self.hosts.get_mut(self.active_host).map(Host::get_control);
+        self.selected_open_files = match (&self.replay, control) {
+            (Some(_), _) => Err(anyhow!("Open files are not part of recordings")),
+            (None, Some(control)) => control.get_proc_open_files(&self.system_metric, pid),
+            (None, None) => Err(anyhow!("No host is shown")),
+        };
+    }
+    // Whether the shown PIDs are live processes that can be signalled and adjusted
+    fn can_control(&self) -> bool {
+        self.replay.is_none()
+            && self
+                .hosts
+                .get(self.active_host)
+                .is_some_and(Host::can_control)
+    }
+    fn refresh_now(&mut self) {
+        if let Some(host) = self.hosts.get_mut(self.active_host) {
+            host.get_sampler().refresh_now();
         }
     }
-}
-
-impl ForgeViewApp {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        cc.egui_ctx.set_fonts(egui::FontDefinitions::default());
-        Self::default()
+    // Only the shown host reads sockets, and only while they are listed or exported
+    fn update_read_sockets(&mut self) {
+        let read_sockets = self.metric_state == AppStates::Connections
+            || self
+                .export_dialog
+                .as_ref()
+                .is_some_and(ExportDialog::exports_sockets);
+        for (index, host) in self.hosts.iter_mut().enumerate() {
+            host.get_sampler()
+                .set_read_sockets(read_sockets && index == self.active_host);
+        }
+    }
+    fn sort_proc_list(&mut self) {
+        let (sort_column, sort_descending) = (self.sort_column, self.sort_descending);
+        self.system_list.sort_by(|(_, proc_a), (_, proc_b)| {
+            sort_column.sort_order(sort_descending, proc_a, proc_b)
+        });
     }
 }
 
@@ -78,27 +550,32 @@ impl eframe::App for ForgeViewApp {
                 eframe::Theme::Light => ctx.set_visuals(Visuals::light()),
             }
         }
+        if let Some(system) = poll_hosts(&mut self.hosts, self.active_host) {
+            self.apply_snapshot(system);
+        }
+        self.update_read_sockets();
         egui::TopBottomPanel::top("Metrics Buttons").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
                 ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
                     if ui.button("Process List").clicked() {
                         self.metric_state = AppStates::ProcList;
-                        match self.system_metric.refresh_system_info() {
-                            Ok(_) => {}
-                            Err(_) => println!("Error: Process lib could not compute metrics!"),
-                        }
-                        self.system_list = self.system_metric.get_procs_as_list();
+                        self.refresh_now();
                         ui.ctx().request_repaint();
                     }
                     if ui.button("Process Tree").clicked() {
                         self.metric_state = AppStates::ProcTree;
-                        match self.system_metric.refresh_system_info() {
-                            Ok(_) => {}
-                            Err(_) => println!("Error: Process lib could not compute metrics!"),
-                        }
-                        self.system_tree = build_process_tree(&self.system_metric);
+                        self.refresh_now();
                         ui.ctx().request_repaint();
                     }
+                    if ui.button("Connections").clicked() {
+                        self.metric_state = AppStates::Connections;
+                        self.refresh_now();
+                        ui.ctx().request_repaint();
+                    }
+                    if let Some(host) = self.hosts.get(self.active_host) {
+                        ui.separator();
+                        ui.label(format!("Host: {}", host.get_name()));
+                    }
                 });
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     if ui.button("🌙").clicked() {
@@ -108,24 +585,64 @@ impl eframe::App for ForgeViewApp {
                             false => ctx.set_visuals(Visuals::light()),
                         }
                     }
+                    if ui.button("Export…").clicked() {
+                        self.export_dialog = Some(ExportDialog::new());
+                    }
+                    let Some(host) = self.hosts.get_mut(self.active_host) else {
+                        return;
+                    };
+                    let sampler = host.get_sampler();
                     if ui.button("⟳").clicked() {
-                        match self.system_metric.refresh_system_info() {
-                            Ok(_) => {}
-                            Err(_) => println!("Error: Process lib could not compute metrics!"),
-                        }
-                        match self.metric_state {
-                            AppStates::ProcList => {
-                                self.system_list = self.system_metric.get_procs_as_list();
-                            }
-                            AppStates::ProcTree => {
-                                self.system_tree = build_process_tree(&self.system_metric);
-                            }
+                        sampler.refresh_now();
+                    }
+                    let pause_label = match sampler.is_paused() {
+                        true => "▶",
+                        false => "⏸",
+                    };
+                    if ui
+                        .button(pause_label)
+                        .on_hover_text("Pause or resume automatic refresh")
+                        .clicked()
+                    {
+                        match sampler.is_paused() {
+                            true => sampler.resume(),
+                            false => sampler.pause(),
                         }
-                        ui.ctx().request_repaint();
                     }
                 });
             });
         });
+        if let Some(replay) = &mut self.replay {
+            if let Some(system) = replay_panel(ctx, replay, &mut self.action_status) {
+                self.apply_snapshot(system);
+            }
+        }
+        if let Some(system) = hosts_panel(ctx, &self.hosts, &mut self.active_host) {
+            // PIDs of the previous host mean nothing on this one
+            self.selected_pid = None;
+            self.pending_signal = None;
+            self.priority_dialog = None;
+            self.affinity_dialog = None;
+            self.apply_snapshot(system);
+        }
+        if matches!(self.metric_state, AppStates::ProcList | AppStates::ProcTree) {
+            egui::TopBottomPanel::top("Process Filter").show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    let filter_response = ui.add(
+                        egui::TextEdit::singleline(&mut self.filter_text)
+                            .hint_text("user == \"postgres\" && cpu > 5 || name ~ \"java.*\"")
+                            .desired_width(f32::INFINITY),
+                    );
+                    if filter_response.changed() {
+                        self.set_filter();
+                    }
+                });
+                if let Some(filter_error) = self.filter.get_error() {
+                    ui.colored_label(ui.visuals().error_fg_color, filter_error);
+                }
+            });
+        }
         egui::TopBottomPanel::bottom("System Usage").show(ctx, |ui| {
             ui.vertical_centered(|ui| {
                 ui.heading("System Usage:");
@@ -139,88 +656,513 @@ impl eframe::App for ForgeViewApp {
                     self.system_metric.get_total_mem_usage(),
                     F32_PRECISION
                 ));
+                let mem_info = self.system_metric.get_mem_info();
+                ui.label(format!(
+                    "Used: {} / {}  Available: {}  Free: {}  Buffers: {}  Cached: {}  Shmem: {}",
+                    format_bytes(mem_info.get_used()),
+                    format_bytes(mem_info.get_total()),
+                    format_bytes(mem_info.get_available()),
+                    format_bytes(mem_info.get_free()),
+                    format_bytes(mem_info.get_buffers()),
+                    format_bytes(mem_info.get_cached()),
+                    format_bytes(mem_info.get_shmem())
+                ));
+                ui.label(format!(
+                    "Swap: {} / {}  Swap cached: {}",
+                    format_bytes(mem_info.get_swap_used()),
+                    format_bytes(mem_info.get_swap_total()),
+                    format_bytes(mem_info.get_swap_cached())
+                ));
+                let cpu_topology = self.system_metric.get_cpu_topology();
+                ui.label(format!(
+                    "Sockets: {}  Cores: {}  Threads: {}",
+                    cpu_topology.get_sockets(),
+                    cpu_topology.get_cores(),
+                    cpu_topology.get_threads()
+                ));
+                cpu_cores_layout(ui, self.system_metric.get_cpu_cores());
+                system_history_chart(
+                    ui,
+                    active_history(
+                        self.replay.as_ref(),
+                        &self.hosts,
+                        self.active_host,
+                        &self.system_metric,
+                    ),
+                );
+                let skipped_procs = self.system_metric.get_skipped_procs_count();
+                if skipped_procs > 0 {
+                    ui.label(format!(
+                        "Skipped {} processes that exited during refresh",
+                        skipped_procs
+                    ));
+                }
+                if let Some(action_status) = &self.action_status {
+                    ui.label(action_status);
+                }
             });
         });
+        let mut signal_request: Option<SignalRequest> = None;
+        let can_control = self.can_control();
         match self.metric_state {
             AppStates::ProcList => {
+                let selected_proc = self
+                    .system_list
+                    .iter()
+                    .find(|(pid, _)| Some(*pid) == self.selected_pid);
+                if let Some((_, proc_info)) = selected_proc {
+                    let mut close_details = false;
+                    let mut reload_open_files = false;
+                    egui::SidePanel::right("Process Details")
+                        .resizable(true)
+                        .show(ctx, |ui| {
+                            ui.horizontal(|ui| {
+                                if ui.button("✖").clicked() {
+                                    close_details = true;
+                                }
+                                if ui.button("⟳ Open files").clicked() {
+                                    reload_open_files = true;
+                                }
+                                // Recorded or captured PIDs may belong to unrelated processes
+                                if !can_control {
+                                    return;
+                                }
+                                if ui.button("Adjust priority…").clicked() {
+                                    let host = &mut self.hosts[self.active_host];
+                                    self.priority_dialog =
+                                        Some(PriorityDialog::new(proc_info, host.get_control()));
+                                }
+                                if ui.button("Edit affinity…").clicked() {
+                                    self.affinity_dialog = Some(AffinityDialog::new(
+                                        proc_info,
+                                        self.system_metric.get_cpu_cores(),
+                                    ));
+                                }
+                            });
+                            details_layout(
+                                ui,
+                                proc_info,
+                                &self.selected_open_files,
+                                active_history(
+                                    self.replay.as_ref(),
+                                    &self.hosts,
+                                    self.active_host,
+                                    &self.system_metric,
+                                ),
+                            );
+                        });
+                    if close_details {
+                        self.selected_pid = None;
+                    } else if reload_open_files {
+                        self.select_proc(proc_info.get_pid());
+                    }
+                }
+                let mut clicked_pid: Option<u32> = None;
                 egui::CentralPanel::default().show(ctx, |ui| {
                     TableBuilder::new(ui)
                         .striped(true)
+                        .sense(egui::Sense::click())
+                        .column(Column::remainder().clip(true).resizable(true))
+                        .column(Column::auto().resizable(true))
+                        .column(Column::remainder().clip(true).resizable(true))
+                        .column(Column::remainder().clip(true).resizable(true))
+                        .column(Column::remainder().clip(true).resizable(true))
                         .column(Column::remainder().clip(true).resizable(true))
+                        .column(Column::auto().resizable(true))
+                        .column(Column::auto().resizable(true))
                         .column(Column::remainder().clip(true).resizable(true))
                         .column(Column::remainder().clip(true).resizable(true))
                         .column(Column::remainder().clip(true).resizable(true))
                         .column(Column::remainder().clip(true).resizable(true))
                         .header(20.0, |mut header| {
+                            let mut sort_changed = false;
+                            for (title, column) in [
+                                ("Name", ProcColumn::Name),
+                                ("PID", ProcColumn::Pid),
+                                ("%CPU", ProcColumn::Cpu),
+                                ("%MEM", ProcColumn::Mem),
+                                ("Path", ProcColumn::Path),
+                                ("User", ProcColumn::User),
+                            ] {
+                                header.col(|ui| {
+                                    sort_changed |= sort_header(
+                                        ui,
+                                        title,
+                                        column,
+                                        &mut self.sort_column,
+                                        &mut self.sort_descending,
+                                    );
+                                });
+                            }
                             header.col(|ui| {
-                                ui.heading("Name");
-                            });
-                            header.col(|ui| {
-                                ui.heading("%CPU");
-                            });
-                            header.col(|ui| {
-                                ui.heading("%MEM");
-                            });
-                            header.col(|ui| {
-                                ui.heading("Path");
+                                ui.heading("PRI");
                             });
                             header.col(|ui| {
-                                ui.heading("User");
+                                ui.heading("NI");
                             });
+                            for (title, column) in [
+                                ("Read/s", ProcColumn::ReadRate),
+                                ("Write/s", ProcColumn::WriteRate),
+                                ("Read", ProcColumn::Read),
+                                ("Written", ProcColumn::Written),
+                            ] {
+                                header.col(|ui| {
+                                    sort_changed |= sort_header(
+                                        ui,
+                                        title,
+                                        column,
+                                        &mut self.sort_column,
+                                        &mut self.sort_descending,
+                                    );
+                                });
+                            }
+                            if sort_changed {
+                                self.sort_proc_list();
+                            }
                         })
                         .body(|body| {
-                            body.rows(20.0, self.system_list.len(), |mut row| {
-                                let row_index = row.index();
+                            let table_rows = list_table_rows(
+                                &self.system_list,
+                                &self.expanded_procs,
+                                self.filter.get_filter(),
+                            );
+                            body.rows(20.0, table_rows.len(), |mut row| {
+                                let (proc_index, thread_index) = table_rows[row.index()];
+                                let (pid, proc_info) = &self.system_list[proc_index];
+                                if let Some(thread_index) = thread_index {
+                                    let thread = &proc_info.get_threads()[thread_index];
+                                    row.col(|ui| {
+                                        ui.label(format!(
+                                            "    ↳ {} - TID: {} [{}] on cpu{}",
+                                            thread.get_name(),
+                                            thread.get_tid(),
+                                            thread.get_state(),
+                                            thread.get_processor()
+                                        ));
+                                    });
+                                    row.col(|_| {});
+                                    row.col(|ui| {
+                                        ui.label(format!(
+                                            "{:.1$}",
+                                            thread.get_cpu_used(),
+                                            F32_PRECISION
+                                        ));
+                                    });
+                                    return;
+                                }
+                                row.set_selected(self.selected_pid == Some(*pid));
                                 row.col(|ui| {
-                                    ui.label(self.system_list[row_index].1.get_name());
+                                    if proc_info.get_threads().len() > 1 {
+                                        let expanded = self.expanded_procs.contains(pid);
+                                        if ui
+                                            .small_button(if expanded { "▼" } else { "▶" })
+                                            .clicked()
+                                        {
+                                            if expanded {
+                                                self.expanded_procs.remove(pid);
+                                            } else {
+                                                self.expanded_procs.insert(*pid);
+                                            }
+                                        }
+                                    }
+                                    ui.label(proc_info.get_name());
+                                });
+                                row.col(|ui| {
+                                    ui.label(pid.to_string());
                                 });
                                 row.col(|ui| {
                                     ui.label(format!(
                                         "{:.1$}",
-                                        self.system_list[row_index].1.get_cpu_used(),
+                                        proc_info.get_cpu_used(),
                                         F32_PRECISION
                                     ));
                                 });
                                 row.col(|ui| {
+                                    let proc_memory = proc_info.get_memory();
                                     ui.label(format!(
                                         "{:.1$}",
-                                        self.system_list[row_index].1.get_mem_used(),
+                                        proc_info.get_mem_used(),
                                         F32_PRECISION
+                                    ))
+                                    .on_hover_text(format!(
+                                        "RSS: {}\nPSS: {}\nUSS: {}\nShared: {}\nSwap: {}",
+                                        format_bytes(proc_memory.get_rss()),
+                                        format_bytes(proc_memory.get_pss()),
+                                        format_bytes(proc_memory.get_uss()),
+                                        format_bytes(proc_memory.get_shared()),
+                                        format_bytes(proc_memory.get_swap())
+                                    ));
+                                });
+                                row.col(|ui| {
+                                    ui.label(proc_info.get_path());
+                                });
+                                row.col(|ui| {
+                                    ui.label(proc_info.get_user());
+                                });
+                                let scheduling = proc_info.get_scheduling();
+                                row.col(|ui| {
+                                    ui.label(scheduling.get_priority().to_string())
+                                        .on_hover_text(scheduling.get_policy().as_str());
+                                });
+                                row.col(|ui| {
+                                    ui.label(scheduling.get_nice().to_string());
+                                });
+                                let proc_io = proc_info.get_io();
+                                row.col(|ui| {
+                                    ui.label(format!(
+                                        "{}/s",
+                                        format_bytes(proc_io.get_read_rate() as u64)
+                                    ));
+                                });
+                                row.col(|ui| {
+                                    ui.label(format!(
+                                        "{}/s",
+                                        format_bytes(proc_io.get_write_rate() as u64)
                                     ));
                                 });
                                 row.col(|ui| {
-                                    ui.label(self.system_list[row_index].1.get_path());
+                                    ui.label(format_bytes(proc_io.get_read_bytes()))
+                                        .on_hover_text(format!(
+                                            "Read syscalls: {}",
+                                            proc_io.get_read_syscalls()
+                                        ));
                                 });
                                 row.col(|ui| {
-                                    ui.label(self.system_list[row_index].1.get_user());
+                                    ui.label(format_bytes(proc_io.get_write_bytes()))
+                                        .on_hover_text(format!(
+                                            "Write syscalls: {}\nCancelled write bytes: {}",
+                                            proc_io.get_write_syscalls(),
+                                            format_bytes(proc_io.get_cancelled_write_bytes())
+                                        ));
                                 });
+                                let response = row.response();
+                                if response.clicked() {
+                                    clicked_pid = Some(*pid);
+                                }
+                                // Recorded or captured PIDs may belong to unrelated processes
+                                if can_control {
+                                    response.context_menu(|ui| {
+                                        signal_menu(
+                                            ui,
+                                            *pid,
+                                            proc_info.get_name(),
+                                            &mut signal_request,
+                                        );
+                                    });
+                                }
                             });
                         });
                 });
+                if let Some(pid) = clicked_pid {
+                    self.select_proc(pid);
+                }
             }
             AppStates::ProcTree => {
                 egui::CentralPanel::default().show(ctx, |ui| {
                     egui::ScrollArea::new([false, true])
                         .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden)
                         .show(ui, |ui| {
-                            tree_layout(ui, &self.system_tree.root);
+                            let visible_tree =
+                                self.filtered_tree.as_ref().unwrap_or(&self.system_tree);
+                            tree_layout(ui, &visible_tree.root, can_control, &mut signal_request);
                         });
                 });
             }
+            AppStates::Connections => {
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    connections_layout(ui, &self.system_list, &mut self.show_unix_sockets);
+                });
+            }
+        }
+        if signal_request.is_some() && self.replay.is_some() {
+            self.action_status = Some("Error: Signals can not be sent while replaying".to_string());
+        } else if let (Some(signal_request), Some(host)) =
+            (signal_request, self.hosts.get_mut(self.active_host))
+        {
+            handle_signal_request(
+                host.get_control(),
+                signal_request,
+                &mut self.pending_signal,
+                &mut self.action_status,
+            );
         }
+        if let Some(host) = self.hosts.get_mut(self.active_host) {
+            let control = host.get_control();
+            confirm_signal_window(
+                ctx,
+                control,
+                &mut self.pending_signal,
+                &mut self.action_status,
+            );
+            priority_window(
+                ctx,
+                control,
+                &mut self.priority_dialog,
+                &mut self.action_status,
+            );
+            affinity_window(
+                ctx,
+                control,
+                &mut self.affinity_dialog,
+                &mut self.action_status,
+            );
+        }
+        export_window(
+            ctx,
+            &mut self.export_dialog,
+            self.filtered_tree.as_ref().unwrap_or(&self.system_tree),
+            &self.system_list,
+            self.filter.get_filter(),
+            &mut self.action_status,
+        );
+    }
+}
+
+// History of the recording while replaying, otherwise of the active host
+fn active_history<'a>(
+    replay: Option<&'a Replay>,
+    hosts: &'a [Host],
+    active_host: usize,
+    system: &'a System,
+) -> &'a History {
+    match (replay, hosts.get(active_host)) {
+        (Some(replay), _) => replay.get_history(),
+        (None, Some(host)) => host.get_history(),
+        (None, None) => system.get_history(),
     }
 }
 
-fn tree_layout(ui: &mut egui::Ui, proc_node: &ProcessTreeNode) {
-    egui::CollapsingHeader::new(format!(
+fn tree_layout(
+    ui: &mut egui::Ui,
+    proc_node: &ProcessTreeNode,
+    can_control: bool,
+    signal_request: &mut Option<SignalRequest>,
+) {
+    let pid = proc_node.proc_info.get_pid();
+    let collapsing_response = egui::CollapsingHeader::new(format!(
         "{} - PID: {}",
         proc_node.proc_info.get_name(),
         proc_node.proc_info.get_pid()
     ))
     .default_open(true)
     .show(ui, |ui| {
+        let threads = proc_node.proc_info.get_threads();
+        if threads.len() > 1 {
+            egui::CollapsingHeader::new(format!("Threads ({})", threads.len()))
+                .id_source(("Threads", proc_node.proc_info.get_pid()))
+                .default_open(false)
+                .show(ui, |ui| {
+                    for thread in threads.iter() {
+                        ui.label(format!(
+                            "{} - TID: {} [{}] on cpu{} - CPU: %{:.5$}",
+                            thread.get_name(),
+                            thread.get_tid(),
+                            thread.get_state(),
+                            thread.get_processor(),
+                            thread.get_cpu_used(),
+                            F32_PRECISION
+                        ));
+                    }
+                });
+        }
         for child in proc_node.children.iter() {
-            tree_layout(ui, child);
+            tree_layout(ui, child, can_control, signal_request);
+        }
+    });
+    // The root node is a placeholder for the whole system, not a real process
+    if pid != 0 && can_control {
+        collapsing_response.header_response.context_menu(|ui| {
+            signal_menu(ui, pid, proc_node.proc_info.get_name(), signal_request);
+        });
+    }
+}
+
+fn sort_header(
+    ui: &mut egui::Ui,
+    title: &str,
+    column: ProcColumn,
+    sort_column: &mut ProcColumn,
+    sort_descending: &mut bool,
+) -> bool {
+    let sort_marker = match (*sort_column == column, *sort_descending) {
+        (true, true) => " ⏷",
+        (true, false) => " ⏶",
+        (false, _) => "",
+    };
+    let clicked = ui
+        .add(
+            egui::Label::new(egui::RichText::new(format!("{}{}", title, sort_marker)).heading())
+                .sense(egui::Sense::click()),
+        )
+        .clicked();
+    if clicked {
+        if *sort_column == column {
+            *sort_descending = !*sort_descending;
+        } else {
+            *sort_column = column;
+            *sort_descending = column.is_descending_by_default();
+        }
+    }
+    clicked
+}
+
+fn list_table_rows(
+    system_list: &[(u32, Process)],
+    expanded_procs: &HashSet<u32>,
+    filter: Option<&Filter>,
+) -> Vec<(usize, Option<usize>)> {
+    let mut table_rows: Vec<(usize, Option<usize>)> = Vec::new();
+    for (proc_index, (pid, proc_info)) in system_list.iter().enumerate() {
+        if filter.is_some_and(|filter| !filter.matches(proc_info)) {
+            continue;
+        }
+        table_rows.push((proc_index, None));
+        if expanded_procs.contains(pid) {
+            table_rows
+                .extend((0..proc_info.get_threads().len()).map(|index| (proc_index, Some(index))));
+        }
+    }
+    table_rows
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f32;
+    let mut unit = 0usize;
+    while value >= 1024f32 && unit < UNITS.len() - 1 {
+        value /= 1024f32;
+        unit += 1;
+    }
+    format!("{:.2$} {}", value, UNITS[unit], F32_PRECISION)
+}
+
+const CPU_CORES_PER_ROW: usize = 4;
+
+fn cpu_cores_layout(ui: &mut egui::Ui, cpu_cores: &[CpuCoreUsage]) {
+    egui::Grid::new("CPU Cores").show(ui, |ui| {
+        for (index, cpu_core) in cpu_cores.iter().enumerate() {
+            ui.add(
+                egui::ProgressBar::new(cpu_core.get_total_usage() / 100f32)
+                    .desired_width(150f32)
+                    .text(format!(
+                        "cpu{}: %{:.2$}",
+                        cpu_core.get_id(),
+                        cpu_core.get_total_usage(),
+                        F32_PRECISION
+                    )),
+            )
+            .on_hover_text(format!(
+                "user: %{:.5$}\nsystem: %{:.5$}\niowait: %{:.5$}\nsteal: %{:.5$}\nirq: %{:.5$}",
+                cpu_core.get_user(),
+                cpu_core.get_system(),
+                cpu_core.get_iowait(),
+                cpu_core.get_steal(),
+                cpu_core.get_irq(),
+                F32_PRECISION
+            ));
+            if (index + 1) % CPU_CORES_PER_ROW == 0 {
+                ui.end_row();
+            }
         }
     });
 }
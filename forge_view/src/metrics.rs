@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Result};
+use process::{encode_openmetrics, Filter, Sampler, SnapshotSource, OPENMETRICS_CONTENT_TYPE};
+use std::time::Duration;
+use tiny_http::{Header, Method, Response, Server};
+
+/// Serves `/metrics` from a background sampler until the process is stopped.
+pub fn run_metrics_server(
+    source: SnapshotSource,
+    listen: &str,
+    allowlist: Option<&str>,
+    interval: Duration,
+) -> Result<()> {
+    let allowlist = allowlist.map(Filter::parse).transpose()?;
+    let server =
+        Server::http(listen).map_err(|err| anyhow!("Could not listen on {}: {}", listen, err))?;
+    println!("Serving metrics on http://{}/metrics", listen);
+    serve_metrics(&server, source, allowlist.as_ref(), interval)
+}
+
+// Answers the requests of an already bound `server`
+fn serve_metrics(
+    server: &Server,
+    mut source: SnapshotSource,
+    allowlist: Option<&Filter>,
+    interval: Duration,
+) -> Result<()> {
+    let content_type = Header::from_bytes("Content-Type", OPENMETRICS_CONTENT_TYPE)
+        .map_err(|_| anyhow!("Invalid Content-Type header"))?;
+    let mut latest = source.next_snapshot()?;
+    let mut sampler = Sampler::spawn(source, interval, || {});
+    for request in server.incoming_requests() {
+        if let Some(system) = sampler.try_recv_latest() {
+            latest = system;
+        }
+        let path = request.url().split('?').next().unwrap_or_default();
+        let response = match (request.method(), path) {
+            (Method::Get, "/metrics") => {
+                Response::from_string(encode_openmetrics(&latest, allowlist))
+                    .with_header(content_type.clone())
+            }
+            _ => Response::from_string("Not Found\n").with_status_code(404),
+        };
+        if let Err(err) = request.respond(response) {
+            println!("Error: Could not answer metrics request: {}", err);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use process::fixture::Fixture;
+    use process::System;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::thread;
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "GET {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+            path
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn metrics_are_served_on_localhost() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        let source = SnapshotSource::from(System::with_root(fixture.get_root()));
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap();
+        thread::spawn(move || {
+            let allowlist = Filter::parse("pid == 2").unwrap();
+            serve_metrics(&server, source, Some(&allowlist), Duration::from_secs(60))
+        });
+        let response = get(address, "/metrics?format=text");
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.contains(OPENMETRICS_CONTENT_TYPE));
+        assert!(response.contains("\r\n\r\n# TYPE forge_view_cpu_usage_percent gauge\n"));
+        assert!(response.contains("forge_view_process_cpu_usage_percent{pid=\"2\","));
+        assert!(!response.contains("{pid=\"3\","));
+        assert!(response.ends_with("# EOF\n"));
+        assert!(get(address, "/other").starts_with("HTTP/1.1 404"));
+    }
+}
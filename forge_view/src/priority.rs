@@ -0,0 +1,146 @@
+use crate::hosts::HostControl;
+use eframe::egui;
+use process::{IoPriority, IoPriorityClass, Process, SchedulingPolicy};
+
+const SCHEDULING_POLICIES: [SchedulingPolicy; 5] = [
+    SchedulingPolicy::Other,
+    SchedulingPolicy::Batch,
+    SchedulingPolicy::Idle,
+    SchedulingPolicy::Fifo,
+    SchedulingPolicy::RoundRobin,
+];
+
+const IO_PRIORITY_CLASSES: [IoPriorityClass; 3] = [
+    IoPriorityClass::RealTime,
+    IoPriorityClass::BestEffort,
+    IoPriorityClass::Idle,
+];
+
+pub struct PriorityDialog {
+    pid: u32,
+    name: String,
+    nice: i32,
+    policy: SchedulingPolicy,
+    rt_priority: u32,
+    io_class: IoPriorityClass,
+    io_level: u8,
+}
+
+impl PriorityDialog {
+    pub fn new(proc_info: &Process, control: &mut HostControl) -> Self {
+        let scheduling = proc_info.get_scheduling();
+        // Processes without an explicit I/O priority get best-effort derived from their nice value
+        let io_priority = control
+            .get_io_priority(proc_info.get_pid())
+            .ok()
+            .filter(|io_priority| io_priority.get_class() != IoPriorityClass::None)
+            .unwrap_or(IoPriority::new(
+                IoPriorityClass::BestEffort,
+                ((scheduling.get_nice() + 20) / 5).clamp(0, 7) as u8,
+            ));
+        Self {
+            pid: proc_info.get_pid(),
+            name: proc_info.get_name().clone(),
+            nice: scheduling.get_nice() as i32,
+            policy: scheduling.get_policy(),
+            rt_priority: scheduling.get_rt_priority().max(1),
+            io_class: io_priority.get_class(),
+            io_level: io_priority.get_level(),
+        }
+    }
+}
+
+pub fn priority_window(
+    ctx: &egui::Context,
+    control: &mut HostControl,
+    priority_dialog: &mut Option<PriorityDialog>,
+    action_status: &mut Option<String>,
+) {
+    let Some(dialog) = priority_dialog else {
+        return;
+    };
+    let mut open = true;
+    egui::Window::new(format!("Priority - {} (PID {})", dialog.name, dialog.pid))
+        .collapsible(false)
+        .resizable(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            egui::Grid::new("Priority Settings").show(ui, |ui| {
+                ui.label("Nice:");
+                ui.add(egui::Slider::new(&mut dialog.nice, -20..=19));
+                if ui.button("Apply").clicked() {
+                    *action_status = Some(status_message(
+                        control.set_nice(dialog.pid, dialog.nice),
+                        format!("Set nice {} on PID {}", dialog.nice, dialog.pid),
+                    ));
+                }
+                ui.end_row();
+                ui.label("Policy:");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("Scheduling Policy")
+                        .selected_text(dialog.policy.as_str())
+                        .show_ui(ui, |ui| {
+                            for policy in SCHEDULING_POLICIES {
+                                ui.selectable_value(&mut dialog.policy, policy, policy.as_str());
+                            }
+                        });
+                    if dialog.policy.is_realtime() {
+                        ui.add(egui::Slider::new(&mut dialog.rt_priority, 1..=99).text("RT"));
+                    }
+                });
+                if ui.button("Apply").clicked() {
+                    *action_status = Some(status_message(
+                        control.set_scheduling_policy(
+                            dialog.pid,
+                            dialog.policy,
+                            dialog.rt_priority,
+                        ),
+                        format!("Set {} on PID {}", dialog.policy.as_str(), dialog.pid),
+                    ));
+                }
+                ui.end_row();
+                ui.label("I/O priority:");
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("I/O Priority Class")
+                        .selected_text(dialog.io_class.as_str())
+                        .show_ui(ui, |ui| {
+                            for io_class in IO_PRIORITY_CLASSES {
+                                ui.selectable_value(
+                                    &mut dialog.io_class,
+                                    io_class,
+                                    io_class.as_str(),
+                                );
+                            }
+                        });
+                    if dialog.io_class != IoPriorityClass::Idle {
+                        ui.add(egui::Slider::new(&mut dialog.io_level, 0..=7).text("level"));
+                    }
+                });
+                if ui.button("Apply").clicked() {
+                    *action_status = Some(status_message(
+                        control.set_io_priority(
+                            dialog.pid,
+                            IoPriority::new(dialog.io_class, dialog.io_level),
+                        ),
+                        format!(
+                            "Set I/O priority {} {} on PID {}",
+                            dialog.io_class.as_str(),
+                            dialog.io_level,
+                            dialog.pid
+                        ),
+                    ));
+                }
+                ui.end_row();
+            });
+        });
+    if !open {
+        *priority_dialog = None;
+    }
+}
+
+fn status_message(result: anyhow::Result<()>, success: String) -> String {
+    match result {
+        Ok(()) => success,
+        Err(err) => format!("Error: {}", err),
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::{anyhow, Result};
+use eframe::egui;
+use process::{History, Recording, System};
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+pub struct Replay {
+    recording: Recording,
+    index: usize,
+    playing: bool,
+    last_step: Instant,
+}
+
+impl Replay {
+    /// Also decodes the first snapshot, so a damaged recording is reported up front.
+    pub fn open<P: AsRef<Path>>(path: P, history_duration: Duration) -> Result<(Self, System)> {
+        let mut recording = Recording::open(path)?;
+        if recording.is_empty() {
+            return Err(anyhow!("Recording holds no snapshots"));
+        }
+        recording.set_history_duration(history_duration);
+        let system = recording.get_snapshot(0)?;
+        Ok((
+            Self {
+                recording,
+                index: 0,
+                playing: false,
+                last_step: Instant::now(),
+            },
+            system,
+        ))
+    }
+    pub fn get_history(&self) -> &History {
+        self.recording.get_history()
+    }
+    fn get_timestamp(&self, index: usize) -> SystemTime {
+        self.recording
+            .get_timestamp(index)
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+    // Recorded time between the current snapshot and the next one
+    fn next_step_delay(&self) -> Option<Duration> {
+        if self.index + 1 >= self.recording.len() {
+            return None;
+        }
+        Some(
+            self.get_timestamp(self.index + 1)
+                .duration_since(self.get_timestamp(self.index))
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Returns the snapshot to show when the replay position changed.
+pub fn replay_panel(
+    ctx: &egui::Context,
+    replay: &mut Replay,
+    action_status: &mut Option<String>,
+) -> Option<System> {
+    let prev_index = replay.index;
+    let last_index = replay.recording.len() - 1;
+    if replay.playing {
+        match replay.next_step_delay() {
+            Some(delay) if replay.last_step.elapsed() >= delay => {
+                replay.index += 1;
+                replay.last_step = Instant::now();
+            }
+            Some(delay) => {
+                ctx.request_repaint_after(delay.saturating_sub(replay.last_step.elapsed()))
+            }
+            None => replay.playing = false,
+        }
+    }
+    egui::TopBottomPanel::top("Replay").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Replay:");
+            if ui.button("⏮").clicked() {
+                replay.index = 0;
+            }
+            if ui.button("◀").clicked() {
+                replay.index = replay.index.saturating_sub(1);
+            }
+            let play_label = match replay.playing {
+                true => "⏸",
+                false => "▶",
+            };
+            if ui.button(play_label).clicked() {
+                replay.playing = !replay.playing;
+                replay.last_step = Instant::now();
+            }
+            if ui.button("▶|").clicked() {
+                replay.index = (replay.index + 1).min(last_index);
+            }
+            if ui.button("⏭").clicked() {
+                replay.index = last_index;
+            }
+            ui.add(
+                egui::Slider::new(&mut replay.index, 0..=last_index)
+                    .show_value(false)
+                    .clamp_to_range(true),
+            );
+            let elapsed = replay
+                .get_timestamp(replay.index)
+                .duration_since(replay.get_timestamp(0))
+                .unwrap_or_default();
+            ui.label(format!(
+                "Snapshot {} / {}  +{:.1}s",
+                replay.index + 1,
+                last_index + 1,
+                elapsed.as_secs_f32()
+            ));
+        });
+    });
+    if replay.index == prev_index {
+        return None;
+    }
+    match replay.recording.get_snapshot(replay.index) {
+        Ok(system) => Some(system),
+        Err(err) => {
+            *action_status = Some(format!("Error: {}", err));
+            replay.playing = false;
+            None
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::hosts::HostControl;
+use eframe::egui;
+use process::control::Signal;
+
+pub struct SignalRequest {
+    pid: u32,
+    name: String,
+    signal: Signal,
+}
+
+impl SignalRequest {
+    fn is_destructive(&self) -> bool {
+        matches!(
+            self.signal,
+            Signal::SIGTERM | Signal::SIGKILL | Signal::SIGINT | Signal::SIGQUIT | Signal::SIGHUP
+        )
+    }
+}
+
+const OTHER_SIGNALS: [Signal; 5] = [
+    Signal::SIGHUP,
+    Signal::SIGINT,
+    Signal::SIGQUIT,
+    Signal::SIGUSR1,
+    Signal::SIGUSR2,
+];
+
+pub fn signal_menu(
+    ui: &mut egui::Ui,
+    pid: u32,
+    name: &str,
+    signal_request: &mut Option<SignalRequest>,
+) {
+    let mut request = |signal: Signal| {
+        *signal_request = Some(SignalRequest {
+            pid,
+            name: name.to_string(),
+            signal,
+        });
+    };
+    for (label, signal) in [
+        ("Terminate", Signal::SIGTERM),
+        ("Kill", Signal::SIGKILL),
+        ("Stop", Signal::SIGSTOP),
+        ("Continue", Signal::SIGCONT),
+    ] {
+        if ui.button(label).clicked() {
+            request(signal);
+            ui.close_menu();
+        }
+    }
+    ui.menu_button("Send signal", |ui| {
+        for signal in OTHER_SIGNALS {
+            if ui.button(signal.as_str()).clicked() {
+                request(signal);
+                ui.close_menu();
+            }
+        }
+    });
+}
+
+/// Sends non-destructive signals right away and keeps destructive ones until confirmed.
+pub fn handle_signal_request(
+    control: &mut HostControl,
+    signal_request: SignalRequest,
+    pending_signal: &mut Option<SignalRequest>,
+    signal_status: &mut Option<String>,
+) {
+    if signal_request.is_destructive() {
+        *pending_signal = Some(signal_request);
+    } else {
+        *signal_status = Some(send_signal_request(control, &signal_request));
+    }
+}
+
+pub fn confirm_signal_window(
+    ctx: &egui::Context,
+    control: &mut HostControl,
+    pending_signal: &mut Option<SignalRequest>,
+    signal_status: &mut Option<String>,
+) {
+    let Some(signal_request) = pending_signal else {
+        return;
+    };
+    let mut answered = false;
+    egui::Window::new("Confirm signal")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "Send {} to {} (PID {})?",
+                signal_request.signal, signal_request.name, signal_request.pid
+            ));
+            ui.horizontal(|ui| {
+                if ui.button("Send").clicked() {
+                    *signal_status = Some(send_signal_request(control, signal_request));
+                    answered = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    answered = true;
+                }
+            });
+        });
+    if answered {
+        *pending_signal = None;
+    }
+}
+
+fn send_signal_request(control: &mut HostControl, signal_request: &SignalRequest) -> String {
+    match control.send_signal(signal_request.pid, signal_request.signal) {
+        Ok(()) => format!(
+            "Sent {} to {} (PID {})",
+            signal_request.signal, signal_request.name, signal_request.pid
+        ),
+        Err(err) => format!("Error: {}", err),
+    }
+}
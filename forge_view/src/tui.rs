@@ -0,0 +1,454 @@
+use crate::headless::{tree_rows, FilterInput, HeadlessOptions, ProcColumn};
+use crate::{format_bytes, F32_PRECISION};
+use anyhow::Result;
+use process::{
+    build_process_tree, filter_process_tree, Filter, Process, ProcessTree, Sampler, SnapshotSource,
+    System,
+};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Alignment, Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Cell, LineGauge, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::HashMap;
+use std::time::Duration;
+
+const CORE_LABEL_WIDTH: usize = 12;
+const PAGE_ROWS: usize = 10;
+const HELP: &str =
+    "q quit  Tab list/tree  ←/→ sort column  r reverse  / filter  F5 refresh  ↑/↓ select";
+
+struct TuiState {
+    system: System,
+    procs: HashMap<u32, Process>,
+    proc_tree: ProcessTree,
+    filtered_tree: Option<ProcessTree>,
+    options: HeadlessOptions,
+    filter: FilterInput,
+    editing_filter: bool,
+    selected_pid: Option<u32>,
+    table_state: TableState,
+    // Depth and PID of the shown processes in display order, sorted once per change
+    rows: Vec<(usize, u32)>,
+}
+
+impl TuiState {
+    fn new(mut system: System, options: HeadlessOptions, filter: Option<Filter>) -> Self {
+        let proc_tree = build_process_tree(&system);
+        let procs = system.get_procs_as_list().into_iter().collect();
+        let mut state = Self {
+            system,
+            procs,
+            proc_tree,
+            filtered_tree: None,
+            options,
+            filter: FilterInput::new(filter),
+            editing_filter: false,
+            selected_pid: None,
+            table_state: TableState::default(),
+            rows: Vec::new(),
+        };
+        state.filter_tree();
+        state
+    }
+    fn apply_snapshot(&mut self, mut system: System) {
+        self.proc_tree = build_process_tree(&system);
+        self.procs = system.get_procs_as_list().into_iter().collect();
+        self.system = system;
+        self.filter_tree();
+    }
+    fn filter_tree(&mut self) {
+        self.filtered_tree = self
+            .filter
+            .get_filter()
+            .map(|filter| filter_process_tree(&self.proc_tree, filter));
+        self.update_rows();
+    }
+    fn set_filter(&mut self) {
+        self.filter
+            .set_text(self.options.filter.as_deref().unwrap_or_default());
+        self.filter_tree();
+    }
+    // Called whenever the processes, the filter, the sort order or the view change
+    fn update_rows(&mut self) {
+        let mut rows: Vec<(usize, &Process)> = Vec::new();
+        if self.options.tree {
+            let proc_tree = self.filtered_tree.as_ref().unwrap_or(&self.proc_tree);
+            tree_rows(&proc_tree.root, 0, &self.options, &mut rows);
+        } else {
+            rows.extend(
+                self.procs
+                    .values()
+                    .filter(|proc_info| {
+                        self.filter
+                            .get_filter()
+                            .is_none_or(|filter| filter.matches(proc_info))
+                    })
+                    .map(|proc_info| (0, proc_info)),
+            );
+            rows.sort_by(|(_, proc_a), (_, proc_b)| self.options.compare(proc_a, proc_b));
+        }
+        self.rows = rows
+            .into_iter()
+            .map(|(depth, proc_info)| (depth, proc_info.get_pid()))
+            .collect();
+    }
+    // Processes in display order, with their depth in the tree view
+    fn rows(&self) -> Vec<(usize, &Process)> {
+        self.rows
+            .iter()
+            .filter_map(|(depth, pid)| Some((*depth, self.procs.get(pid)?)))
+            .collect()
+    }
+    // Follows the selected process when rows move between refreshes
+    fn sync_selection(&mut self) {
+        let pids: Vec<u32> = self.rows.iter().map(|(_, pid)| *pid).collect();
+        // Stay at the same row when the selected process exited or was filtered out
+        let index = self
+            .selected_pid
+            .and_then(|selected_pid| pids.iter().position(|pid| *pid == selected_pid))
+            .or(self.table_state.selected())
+            .unwrap_or_default()
+            .min(pids.len().saturating_sub(1));
+        self.selected_pid = pids.get(index).copied();
+        self.table_state.select(self.selected_pid.map(|_| index));
+    }
+    fn select_row(&mut self, index: usize) {
+        let index = index.min(self.rows.len().saturating_sub(1));
+        self.selected_pid = self.rows.get(index).map(|(_, pid)| *pid);
+        self.table_state.select(self.selected_pid.map(|_| index));
+    }
+    fn move_selection(&mut self, offset: isize) {
+        let index = self.table_state.selected().unwrap_or_default();
+        self.select_row(index.saturating_add_signed(offset));
+    }
+    fn cycle_sort_column(&mut self, offset: isize) {
+        let columns = &self.options.columns;
+        let position = columns
+            .iter()
+            .position(|column| *column == self.options.sort)
+            .unwrap_or_default() as isize;
+        let position = (position + offset).rem_euclid(columns.len() as isize) as usize;
+        self.options.sort = columns[position];
+        self.options.descending = self.options.sort.is_descending_by_default();
+        self.update_rows();
+    }
+    /// Returns true when the user asked to quit.
+    fn handle_key(&mut self, key: KeyEvent, sampler: &Sampler) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return true;
+        }
+        if self.editing_filter {
+            let filter_text = self.options.filter.get_or_insert_with(String::new);
+            match key.code {
+                KeyCode::Char(character) => filter_text.push(character),
+                KeyCode::Backspace => {
+                    filter_text.pop();
+                }
+                KeyCode::Enter => self.editing_filter = false,
+                KeyCode::Esc => {
+                    filter_text.clear();
+                    self.editing_filter = false;
+                }
+                _ => return false,
+            }
+            self.set_filter();
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Tab => {
+                self.options.tree = !self.options.tree;
+                self.update_rows();
+            }
+            KeyCode::Left | KeyCode::Char('<') => self.cycle_sort_column(-1),
+            KeyCode::Right | KeyCode::Char('>') => self.cycle_sort_column(1),
+            KeyCode::Char('r') => {
+                self.options.descending = !self.options.descending;
+                self.update_rows();
+            }
+            KeyCode::Char('/') => self.editing_filter = true,
+            KeyCode::F(5) => sampler.refresh_now(),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(PAGE_ROWS as isize)),
+            KeyCode::PageDown => self.move_selection(PAGE_ROWS as isize),
+            KeyCode::Home => self.select_row(0),
+            KeyCode::End => self.select_row(usize::MAX),
+            _ => {}
+        }
+        false
+    }
+}
+
+/// Shows the process list or tree in the terminal until the user quits, refreshing every `interval`.
+pub fn run_tui(
+    mut source: SnapshotSource,
+    options: HeadlessOptions,
+    interval: Duration,
+) -> Result<()> {
+    let filter = options.filter.as_deref().map(Filter::parse).transpose()?;
+    let system = source.next_snapshot()?;
+    let mut sampler = Sampler::spawn(source, interval, || {});
+    let mut state = TuiState::new(system, options, filter);
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut state, &mut sampler);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    state: &mut TuiState,
+    sampler: &mut Sampler,
+) -> Result<()> {
+    loop {
+        if let Some(system) = sampler.try_recv_latest() {
+            state.apply_snapshot(system);
+        }
+        state.sync_selection();
+        terminal.draw(|frame| draw(frame, state))?;
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press && state.handle_key(key, sampler) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, state: &mut TuiState) {
+    let cores_per_line = (frame.area().width as usize / CORE_LABEL_WIDTH).max(1);
+    let core_lines = state.system.get_cpu_cores().len().div_ceil(cores_per_line);
+    let [gauges_area, summary_area, view_area, table_area, status_area] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(3 + core_lines as u16),
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    draw_summary(frame, state, gauges_area, summary_area, cores_per_line);
+
+    let rows = state.rows();
+    let view = match state.options.tree {
+        true => "Process Tree",
+        false => "Process List",
+    };
+    let mut view_line = vec![Span::styled(
+        view,
+        Style::default().add_modifier(Modifier::BOLD),
+    )];
+    view_line.push(Span::raw(format!("  {} processes", rows.len())));
+    if let Some(filter_text) = state
+        .options
+        .filter
+        .as_deref()
+        .filter(|text| !text.is_empty())
+    {
+        view_line.push(Span::raw(format!("  filter: {}", filter_text)));
+    }
+    frame.render_widget(Line::from(view_line), view_area);
+
+    let columns = &state.options.columns;
+    let header = Row::new(columns.iter().map(|column| {
+        let mut title = column.header().to_string();
+        if *column == state.options.sort {
+            title.push(match state.options.descending {
+                true => '▼',
+                false => '▲',
+            });
+        }
+        align_cell(title, column.is_text())
+    }))
+    .style(Style::default().add_modifier(Modifier::REVERSED));
+    let table_rows = rows.iter().map(|(depth, proc_info)| {
+        Row::new(columns.iter().map(|column| {
+            let value = match column {
+                ProcColumn::Name => {
+                    format!("{}{}", "  ".repeat(*depth), column.value(proc_info))
+                }
+                _ => column.value(proc_info),
+            };
+            align_cell(value, column.is_text())
+        }))
+    });
+    let widths = columns.iter().map(|column| match column {
+        ProcColumn::Name | ProcColumn::Path => Constraint::Fill(1),
+        ProcColumn::User => Constraint::Length(10),
+        _ => Constraint::Length((column.header().len() + 1).max(8) as u16),
+    });
+    let table = Table::new(table_rows, widths)
+        .header(header)
+        .row_highlight_style(Style::default().bg(Color::DarkGray));
+    frame.render_stateful_widget(table, table_area, &mut state.table_state);
+
+    let status = match (state.filter.get_error(), state.editing_filter) {
+        (Some(filter_error), _) => Line::styled(
+            format!("Error: {}", filter_error),
+            Style::default().fg(Color::Red),
+        ),
+        (None, true) => Line::raw(format!(
+            "Filter: {}_  (Enter keep, Esc clear)",
+            state.options.filter.as_deref().unwrap_or_default()
+        )),
+        (None, false) => Line::raw(HELP),
+    };
+    frame.render_widget(status, status_area);
+}
+
+// Same lines as the "System Usage" panel of the window
+fn draw_summary(
+    frame: &mut Frame,
+    state: &TuiState,
+    gauges_area: Rect,
+    summary_area: Rect,
+    cores_per_line: usize,
+) {
+    let system = &state.system;
+    let [cpu_area, mem_area] =
+        Layout::horizontal([Constraint::Fill(1), Constraint::Fill(1)]).areas(gauges_area);
+    for (area, label, usage) in [
+        (cpu_area, "CPU", system.get_total_cpu_usage()),
+        (mem_area, "Memory", system.get_total_mem_usage()),
+    ] {
+        let gauge = LineGauge::default()
+            .label(format!("{} %{:.2$}", label, usage, F32_PRECISION))
+            .ratio((usage as f64 / 100f64).clamp(0f64, 1f64))
+            .filled_style(Style::default().fg(Color::Green));
+        frame.render_widget(gauge, area);
+    }
+    let mem_info = system.get_mem_info();
+    let cpu_topology = system.get_cpu_topology();
+    let mut lines = vec![
+        Line::raw(format!(
+            "Used: {} / {}  Available: {}  Free: {}  Buffers: {}  Cached: {}  Shmem: {}",
+            format_bytes(mem_info.get_used()),
+            format_bytes(mem_info.get_total()),
+            format_bytes(mem_info.get_available()),
+            format_bytes(mem_info.get_free()),
+            format_bytes(mem_info.get_buffers()),
+            format_bytes(mem_info.get_cached()),
+            format_bytes(mem_info.get_shmem())
+        )),
+        Line::raw(format!(
+            "Swap: {} / {}  Swap cached: {}",
+            format_bytes(mem_info.get_swap_used()),
+            format_bytes(mem_info.get_swap_total()),
+            format_bytes(mem_info.get_swap_cached())
+        )),
+        Line::raw(format!(
+            "Sockets: {}  Cores: {}  Threads: {}",
+            cpu_topology.get_sockets(),
+            cpu_topology.get_cores(),
+            cpu_topology.get_threads()
+        )),
+    ];
+    for cores in system.get_cpu_cores().chunks(cores_per_line) {
+        lines.push(Line::raw(
+            cores
+                .iter()
+                .map(|core| {
+                    format!(
+                        "{:<1$}",
+                        format!("cpu{} {:.1}%", core.get_id(), core.get_total_usage()),
+                        CORE_LABEL_WIDTH
+                    )
+                })
+                .collect::<String>(),
+        ));
+    }
+    frame.render_widget(Paragraph::new(lines), summary_area);
+}
+
+fn align_cell(value: String, is_text: bool) -> Cell<'static> {
+    let alignment = match is_text {
+        true => Alignment::Left,
+        false => Alignment::Right,
+    };
+    Cell::from(Line::from(value).alignment(alignment))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use process::fixture::Fixture;
+
+    // Processes 1 to 6 running 2, 3, 4, 1, 2 and 3 threads
+    fn fixture_state() -> (TuiState, Sampler, Fixture) {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(6).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        let options = HeadlessOptions {
+            columns: vec![ProcColumn::Pid, ProcColumn::Name, ProcColumn::Threads],
+            sort: ProcColumn::Pid,
+            descending: false,
+            filter: None,
+            tree: false,
+        };
+        let sampler = Sampler::spawn(system.clone(), Duration::from_secs(3600), || {});
+        (TuiState::new(system, options, None), sampler, fixture)
+    }
+
+    fn press(state: &mut TuiState, sampler: &Sampler, keys: &[KeyCode]) {
+        for key in keys {
+            state.handle_key(KeyEvent::new(*key, KeyModifiers::NONE), sampler);
+        }
+    }
+
+    fn row_pids(state: &TuiState) -> Vec<u32> {
+        state
+            .rows()
+            .iter()
+            .map(|(_, proc_info)| proc_info.get_pid())
+            .collect()
+    }
+
+    #[test]
+    fn rows_are_sorted_when_the_sort_order_changes() {
+        let (mut state, sampler, _fixture) = fixture_state();
+        assert_eq!(row_pids(&state), vec![1, 2, 3, 4, 5, 6]);
+        press(&mut state, &sampler, &[KeyCode::Right, KeyCode::Right]);
+        assert!(state.options.sort == ProcColumn::Threads);
+        assert_eq!(row_pids(&state), vec![3, 2, 6, 1, 5, 4]);
+        press(&mut state, &sampler, &[KeyCode::Char('r')]);
+        assert_eq!(row_pids(&state), vec![4, 1, 5, 2, 6, 3]);
+    }
+
+    #[test]
+    fn rows_follow_the_filter_and_the_view() {
+        let (mut state, sampler, _fixture) = fixture_state();
+        let mut keys = vec![KeyCode::Char('/')];
+        keys.extend("threads > 2".chars().map(KeyCode::Char));
+        keys.push(KeyCode::Enter);
+        press(&mut state, &sampler, &keys);
+        assert_eq!(row_pids(&state), vec![2, 3, 6]);
+        press(&mut state, &sampler, &[KeyCode::Tab]);
+        // The tree keeps init as the ancestor of the matches
+        assert_eq!(state.rows, vec![(0, 1), (1, 2), (1, 3), (1, 6)]);
+    }
+
+    #[test]
+    fn selection_follows_the_process_between_snapshots() {
+        let (mut state, sampler, fixture) = fixture_state();
+        state.select_row(2);
+        assert_eq!(state.selected_pid, Some(3));
+        press(&mut state, &sampler, &[KeyCode::Char('r')]);
+        state.sync_selection();
+        assert_eq!(state.selected_pid, Some(3));
+        assert_eq!(state.table_state.selected(), Some(3));
+        // 6 exits, so 3 moves up a row
+        std::fs::remove_dir_all(fixture.get_root().join("proc/6")).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        state.apply_snapshot(system);
+        state.sync_selection();
+        assert_eq!(row_pids(&state), vec![5, 4, 3, 2, 1]);
+        assert_eq!(state.selected_pid, Some(3));
+        assert_eq!(state.table_state.selected(), Some(2));
+    }
+}
@@ -0,0 +1,55 @@
+//! Measures `refresh_system_info` on a generated procfs tree with 5,000 processes, each with
+//! several threads, open files and a TCP socket.
+//!
+//! Run with `cargo bench -p process`, the process count can be passed as an argument.
+
+#[path = "../src/fixture.rs"]
+mod fixture;
+
+use fixture::Fixture;
+use process::System;
+use std::time::{Duration, Instant};
+
+const DEFAULT_PROCS: u32 = 5000;
+const WARM_REFRESHES: u32 = 20;
+
+fn time_refresh(system: &mut System) -> Duration {
+    let start = Instant::now();
+    if let Err(err) = system.refresh_system_info() {
+        eprintln!("Error: Refresh failed: {}", err);
+        std::process::exit(1);
+    }
+    start.elapsed()
+}
+
+fn main() {
+    // `cargo bench` passes `--bench`, only a number is taken as the process count
+    let procs = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_PROCS);
+    let fixture = match Fixture::new().and_then(|fixture| {
+        fixture.write_procs(procs)?;
+        Ok(fixture)
+    }) {
+        Ok(fixture) => fixture,
+        Err(err) => {
+            eprintln!("Error: Could not write procfs tree: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let mut system = System::with_root(fixture.get_root());
+    let cold = time_refresh(&mut system);
+    let warm: Duration = (0..WARM_REFRESHES).map(|_| time_refresh(&mut system)).sum();
+    println!(
+        "Processes read: {} of {}",
+        system.get_procs_as_list().len(),
+        procs
+    );
+    println!("First refresh:  {:.2?}", cold);
+    println!(
+        "Later refreshes: {:.2?} on average over {}",
+        warm / WARM_REFRESHES,
+        WARM_REFRESHES
+    );
+}
@@ -0,0 +1,946 @@
+use crate::control::{self, Signal};
+use crate::{History, IoPriority, OpenFile, Sampler, SchedulingPolicy, System};
+use anyhow::{anyhow, Result};
+use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+use nix::sys::stat::{umask, Mode};
+use nix::unistd::getuid;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Version of the agent wire protocol, exchanged in the `hello` messages.
+pub const AGENT_PROTOCOL_VERSION: u32 = 1;
+const TCP_TIMEOUT: Duration = Duration::from_secs(10);
+// Time a client has to send its hello after connecting
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+// Longest request line read, a cpu list of 4096 CPUs fits well within it
+const MAX_REQUEST_LINE: u64 = 64 * 1024;
+// Longest response line read, well above the history of every process of a busy host
+const MAX_RESPONSE_LINE: u64 = 256 * 1024 * 1024;
+// Clients served at once over the Unix socket and TCP together
+const MAX_CLIENTS: usize = 64;
+// Time the agent keeps reading sockets after the last client asked for them
+const READ_SOCKETS_FOR: Duration = Duration::from_secs(30);
+
+/// A request sent to an agent.
+///
+/// The protocol is newline delimited JSON over a Unix domain socket: every request
+/// and every response is one JSON value on its own line. Messages with fields are an
+/// object keyed by the message name, e.g. `{"history":{"pid":42}}`, and messages
+/// without fields are just their name as a string, e.g. `"snapshot"`.
+///
+/// A client first sends `{"hello":{"version":1}}`; the agent answers with its own
+/// `hello`, or with an `error` and closes the connection when it does not speak that
+/// version. Over TCP the hello must also carry the agent's shared `token`, e.g.
+/// `{"hello":{"version":1,"token":"..."}}`. After that each request gets exactly one
+/// response, in order:
+///
+/// - `snapshot` → `snapshot` with the latest `system` and the `timestamp` it was taken at
+/// - `read_sockets` → `ok`, sockets of processes are included in snapshots from the next
+///   refresh on, until no client asked for them for 30 seconds
+/// - `history` with an optional `pid` → `history`, all process samples when `pid` is null
+/// - `open_files` with a `pid` → `open_files`
+/// - `io_priority` with a `pid` → `io_priority`, which procfs does not show
+/// - `signal` with a `pid` and a `signal` name such as `"SIGTERM"` → `ok`
+/// - `set_nice`, `set_io_priority`, `set_scheduling_policy`, `set_cpu_affinity` → `ok`
+///
+/// Any request can also be answered with `{"error":{"message":"..."}}`.
+/// The Unix socket is only accessible to the user running the agent. TCP traffic,
+/// including the token, is not encrypted, so expose it on trusted networks or
+/// through a tunnel only. TCP clients may only read unless the agent allows control,
+/// `signal` and the `set_*` requests are answered with an `error` otherwise.
+///
+/// Requests are at most 64 KiB long, the hello must arrive within 10 seconds of
+/// connecting and the agent serves at most 64 clients at once. Clients over these
+/// limits get an `error` and are disconnected. [`AgentClient`] in turn reads responses
+/// of at most 256 MiB.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Request {
+    Hello {
+        version: u32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    Snapshot,
+    ReadSockets,
+    History {
+        pid: Option<u32>,
+    },
+    OpenFiles {
+        pid: u32,
+    },
+    IoPriority {
+        pid: u32,
+    },
+    Signal {
+        pid: u32,
+        signal: String,
+    },
+    SetNice {
+        pid: u32,
+        nice: i32,
+    },
+    SetIoPriority {
+        pid: u32,
+        io_priority: IoPriority,
+    },
+    SetSchedulingPolicy {
+        pid: u32,
+        policy: SchedulingPolicy,
+        rt_priority: u32,
+    },
+    SetCpuAffinity {
+        pid: u32,
+        cpus: Vec<usize>,
+    },
+}
+
+impl Request {
+    /// Whether the request acts on a process instead of reading it.
+    fn is_control(&self) -> bool {
+        matches!(
+            self,
+            Request::Signal { .. }
+                | Request::SetNice { .. }
+                | Request::SetIoPriority { .. }
+                | Request::SetSchedulingPolicy { .. }
+                | Request::SetCpuAffinity { .. }
+        )
+    }
+}
+
+/// A response sent by an agent, see [`Request`].
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Hello {
+        version: u32,
+    },
+    Snapshot {
+        system: Box<System>,
+        timestamp: SystemTime,
+    },
+    History {
+        history: History,
+    },
+    OpenFiles {
+        open_files: Vec<OpenFile>,
+    },
+    IoPriority {
+        io_priority: IoPriority,
+    },
+    Ok,
+    Error {
+        message: String,
+    },
+}
+
+/// `$XDG_RUNTIME_DIR/forge_view.sock`, or a per-user socket in `/tmp`. Another user may
+/// create the latter first, so [`AgentClient::connect`] only talks to agents of its own user.
+pub fn default_agent_socket() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => Path::new(&runtime_dir).join("forge_view.sock"),
+        None => env::temp_dir().join(format!("forge_view-{}.sock", getuid())),
+    }
+}
+
+struct AgentState {
+    sampler: Sampler,
+    latest: System,
+    read_sockets_until: Option<Instant>,
+}
+
+impl AgentState {
+    fn new(system: System, interval: Duration) -> Self {
+        Self {
+            sampler: Sampler::spawn(system.clone(), interval, || {}),
+            latest: system,
+            read_sockets_until: None,
+        }
+    }
+    // Every client shares the sampling, so sockets are read while any of them asks
+    fn update_read_sockets(&mut self, asked: bool) {
+        let now = Instant::now();
+        if asked {
+            self.read_sockets_until = Some(now + READ_SOCKETS_FOR);
+        }
+        let read_sockets = self.read_sockets_until.is_some_and(|until| until > now);
+        self.sampler.set_read_sockets(read_sockets);
+    }
+    fn get_latest(&mut self) -> (&System, &History) {
+        if let Some(system) = self.sampler.try_recv_latest() {
+            self.latest = system;
+        }
+        (&self.latest, self.sampler.get_history())
+    }
+}
+
+/// TCP address an agent listens on, and the token its clients must present.
+pub struct TcpListen {
+    pub address: String,
+    pub token: String,
+    /// Lets TCP clients signal and reprioritize processes, as the agent's user.
+    pub allow_control: bool,
+}
+
+// What a client must present and what it may do once greeted
+#[derive(Clone)]
+struct ClientAccess {
+    token: Option<Arc<str>>,
+    allow_control: bool,
+}
+
+// Clients of the Unix socket need no token and may control processes
+const UNIX_CLIENT_ACCESS: ClientAccess = ClientAccess {
+    token: None,
+    allow_control: true,
+};
+
+// Streams clients are served on, to set timeouts on the Unix and TCP ones alike
+trait ClientStream: Read + Write + Sized + Send + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl ClientStream for UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+impl ClientStream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+// Holds one of the MAX_CLIENTS places until the client's thread ends
+struct ClientSlot {
+    clients: Arc<AtomicUsize>,
+}
+
+impl ClientSlot {
+    fn acquire(clients: &Arc<AtomicUsize>) -> Option<Self> {
+        clients
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < MAX_CLIENTS).then_some(count + 1)
+            })
+            .ok()
+            .map(|_| Self {
+                clients: Arc::clone(clients),
+            })
+    }
+}
+
+impl Drop for ClientSlot {
+    fn drop(&mut self) {
+        self.clients.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Samples `system` every `interval` and answers clients on the socket at `path`,
+/// and over TCP when `tcp` is set, until the process is stopped. A socket left
+/// behind by a stopped agent is replaced.
+pub fn run_agent<P: AsRef<Path>>(
+    mut system: System,
+    path: P,
+    tcp: Option<TcpListen>,
+    interval: Duration,
+) -> Result<()> {
+    let path = path.as_ref();
+    let tcp_listener = match &tcp {
+        Some(tcp) => Some(
+            TcpListener::bind(&tcp.address)
+                .map_err(|err| anyhow!("Could not listen on {}: {}", tcp.address, err))?,
+        ),
+        None => None,
+    };
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(anyhow!(
+                "An agent is already listening on {}",
+                path.display()
+            ));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = bind_private(path)?;
+    system.refresh_system_info()?;
+    let state = Arc::new(Mutex::new(AgentState::new(system, interval)));
+    let clients = Arc::new(AtomicUsize::new(0));
+    if let (Some(tcp_listener), Some(tcp)) = (tcp_listener, tcp) {
+        let (state, clients) = (Arc::clone(&state), Arc::clone(&clients));
+        let access = ClientAccess {
+            token: Some(Arc::from(tcp.token)),
+            allow_control: tcp.allow_control,
+        };
+        thread::spawn(move || {
+            for stream in tcp_listener.incoming() {
+                match stream {
+                    Ok(stream) => spawn_client(stream, &state, &access, &clients),
+                    Err(err) => println!("Error: Could not accept agent client: {}", err),
+                }
+            }
+        });
+    }
+    for stream in listener.incoming() {
+        // Running out of descriptors or a client hanging up early only affects that client
+        match stream {
+            Ok(stream) => spawn_client(stream, &state, &UNIX_CLIENT_ACCESS, &clients),
+            Err(err) => println!("Error: Could not accept agent client: {}", err),
+        }
+    }
+    Ok(())
+}
+
+// Binds with a umask leaving the socket to its owner, setting its permissions afterwards
+// would let other users connect in between. No other thread runs yet to share the umask.
+fn bind_private(path: &Path) -> Result<UnixListener> {
+    let umask_before = umask(Mode::from_bits_truncate(0o077));
+    let listener = UnixListener::bind(path);
+    umask(umask_before);
+    Ok(listener?)
+}
+
+fn spawn_client<S: ClientStream>(
+    mut stream: S,
+    state: &Arc<Mutex<AgentState>>,
+    access: &ClientAccess,
+    clients: &Arc<AtomicUsize>,
+) {
+    let Some(slot) = ClientSlot::acquire(clients) else {
+        let response = Response::Error {
+            message: format!("The agent already serves {} clients", MAX_CLIENTS),
+        };
+        if let Err(err) = write_message(&mut stream, &response) {
+            println!("Error: Could not turn away agent client: {}", err);
+        }
+        return;
+    };
+    let (state, access) = (Arc::clone(state), access.clone());
+    thread::spawn(move || {
+        let _slot = slot;
+        if let Err(err) = serve_stream(stream, &state, &access) {
+            println!("Error: Agent client failed: {}", err);
+        }
+    });
+}
+
+fn serve_stream<S: ClientStream>(
+    stream: S,
+    state: &Mutex<AgentState>,
+    access: &ClientAccess,
+) -> Result<()> {
+    // A client that never greets must not hold its thread forever, greeted clients may
+    // stay idle while their viewer is paused
+    stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+    let reader = BufReader::new(stream.try_clone()?);
+    let timeout_stream = stream.try_clone()?;
+    serve_client(reader, stream, state, access, || {
+        timeout_stream.set_read_timeout(None)
+    })
+}
+
+// Compares as many bytes as the longer token holds, so the time taken tells neither how much
+// of the token matched nor how long it is
+fn tokens_match(given: &str, expected: &str) -> bool {
+    let (given, expected) = (given.as_bytes(), expected.as_bytes());
+    let len = given.len().max(expected.len());
+    let diff = (0..len).fold(given.len() ^ expected.len(), |diff, index| {
+        let given = given.get(index).copied().unwrap_or(0);
+        let expected = expected.get(index).copied().unwrap_or(0);
+        diff | (given ^ expected) as usize
+    });
+    diff == 0
+}
+
+fn is_authorized(token: Option<&str>, given_token: Option<&str>) -> bool {
+    match (token, given_token) {
+        (None, _) => true,
+        (Some(token), Some(given_token)) => tokens_match(given_token, token),
+        (Some(_), None) => false,
+    }
+}
+
+// Reads one line of at most MAX_REQUEST_LINE bytes, returns false at the end of the stream
+fn read_request_line<R: BufRead>(reader: &mut R, line: &mut String) -> Result<bool> {
+    line.clear();
+    // Only the hello is read with a timeout
+    let read = reader
+        .take(MAX_REQUEST_LINE)
+        .read_line(line)
+        .map_err(|err| match err.kind() {
+            ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+                anyhow!(
+                    "No hello request within {} seconds",
+                    HELLO_TIMEOUT.as_secs()
+                )
+            }
+            _ => err.into(),
+        })?;
+    if read as u64 == MAX_REQUEST_LINE && !line.ends_with('\n') {
+        return Err(anyhow!(
+            "Requests may not be longer than {} bytes",
+            MAX_REQUEST_LINE
+        ));
+    }
+    Ok(read > 0)
+}
+
+fn serve_client<R: BufRead, W: Write, F: FnOnce() -> io::Result<()>>(
+    mut reader: R,
+    mut writer: W,
+    state: &Mutex<AgentState>,
+    access: &ClientAccess,
+    on_greeted: F,
+) -> Result<()> {
+    let mut greeted = false;
+    let mut on_greeted = Some(on_greeted);
+    let mut line = String::new();
+    loop {
+        let request = match read_request_line(&mut reader, &mut line) {
+            Ok(true) => serde_json::from_str::<Request>(&line),
+            Ok(false) => return Ok(()),
+            Err(err) => {
+                // The rest of an overlong line can not be told apart from the next request
+                write_message(
+                    &mut writer,
+                    &Response::Error {
+                        message: err.to_string(),
+                    },
+                )?;
+                return Ok(());
+            }
+        };
+        let response = match request {
+            Ok(Request::Hello {
+                token: given_token, ..
+            }) if !is_authorized(access.token.as_deref(), given_token.as_deref()) => {
+                Response::Error {
+                    message: String::from("Invalid token"),
+                }
+            }
+            Ok(Request::Hello { version, .. }) if version == AGENT_PROTOCOL_VERSION => {
+                if let Some(on_greeted) = on_greeted.take() {
+                    on_greeted()?;
+                }
+                greeted = true;
+                Response::Hello {
+                    version: AGENT_PROTOCOL_VERSION,
+                }
+            }
+            Ok(Request::Hello { version, .. }) => Response::Error {
+                message: format!(
+                    "Unsupported protocol version {}, the agent speaks version {}",
+                    version, AGENT_PROTOCOL_VERSION
+                ),
+            },
+            Ok(_) if !greeted => Response::Error {
+                message: String::from("Expected a hello request first"),
+            },
+            Ok(request) if request.is_control() && !access.allow_control => Response::Error {
+                message: String::from(
+                    "Process control is disabled for TCP clients, start the agent with \
+                     --allow-control to enable it",
+                ),
+            },
+            Ok(request) => handle_request(request, state).unwrap_or_else(|err| Response::Error {
+                message: err.to_string(),
+            }),
+            Err(err) => Response::Error {
+                message: format!("Invalid request: {}", err),
+            },
+        };
+        write_message(&mut writer, &response)?;
+        if !greeted {
+            return Ok(());
+        }
+    }
+}
+
+fn handle_request(request: Request, state: &Mutex<AgentState>) -> Result<Response> {
+    let mut state = state
+        .lock()
+        .map_err(|_| anyhow!("Agent state is poisoned"))?;
+    state.update_read_sockets(matches!(request, Request::ReadSockets));
+    let (latest, history) = state.get_latest();
+    // PIDs of a captured root may belong to unrelated processes of this machine
+    let is_live = request.is_control() || matches!(request, Request::IoPriority { .. });
+    if is_live && latest.get_root() != Path::new("/") {
+        return Err(anyhow!(
+            "The agent reads {}, its processes can not be controlled",
+            latest.get_root().display()
+        ));
+    }
+    let response = match request {
+        Request::Hello { .. } => Response::Hello {
+            version: AGENT_PROTOCOL_VERSION,
+        },
+        Request::ReadSockets => Response::Ok,
+        Request::Snapshot => Response::Snapshot {
+            system: Box::new(latest.clone()),
+            timestamp: history
+                .get_system_samples()
+                .back()
+                .map(|sample| sample.get_timestamp())
+                .unwrap_or_else(SystemTime::now),
+        },
+        Request::History { pid } => Response::History {
+            history: match pid {
+                Some(pid) => history.with_pid(pid),
+                None => history.clone(),
+            },
+        },
+        Request::OpenFiles { pid } => Response::OpenFiles {
+            open_files: latest.get_proc_open_files(pid)?,
+        },
+        Request::IoPriority { pid } => Response::IoPriority {
+            io_priority: control::get_io_priority(pid)?,
+        },
+        Request::Signal { pid, signal } => {
+            let signal =
+                Signal::from_str(&signal).map_err(|_| anyhow!("Unknown signal {}", signal))?;
+            control::send_signal(pid, signal)?;
+            Response::Ok
+        }
+        Request::SetNice { pid, nice } => {
+            control::set_nice(pid, nice)?;
+            Response::Ok
+        }
+        Request::SetIoPriority { pid, io_priority } => {
+            control::set_io_priority(pid, io_priority)?;
+            Response::Ok
+        }
+        Request::SetSchedulingPolicy {
+            pid,
+            policy,
+            rt_priority,
+        } => {
+            control::set_scheduling_policy(pid, policy, rt_priority)?;
+            Response::Ok
+        }
+        Request::SetCpuAffinity { pid, cpus } => {
+            control::set_cpu_affinity(pid, &cpus)?;
+            Response::Ok
+        }
+    };
+    Ok(response)
+}
+
+fn write_message<W: Write, T: Serialize>(writer: &mut W, message: &T) -> Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line)?;
+    Ok(())
+}
+
+// Requests may carry control, and snapshots may be forged, so an agent of another user is
+// refused, e.g. one that took over a socket path in `/tmp`
+fn check_peer_is_own_user(stream: &UnixStream, path: &Path) -> Result<()> {
+    let peer_uid = getsockopt(stream, PeerCredentials)?.uid();
+    if peer_uid != getuid().as_raw() {
+        return Err(anyhow!(
+            "The agent on {} runs as uid {}, not as this user",
+            path.display(),
+            peer_uid
+        ));
+    }
+    Ok(())
+}
+
+// Reads one line of at most `max_len` bytes, like the agent does with requests
+fn read_response_line<R: BufRead>(reader: &mut R, line: &mut String, max_len: u64) -> Result<()> {
+    let read = reader.take(max_len).read_line(line)?;
+    if read == 0 {
+        return Err(anyhow!("The agent closed the connection"));
+    }
+    if read as u64 == max_len && !line.ends_with('\n') {
+        return Err(anyhow!(
+            "The agent sent a response longer than {} bytes",
+            max_len
+        ));
+    }
+    Ok(())
+}
+
+/// Connection to an agent, used instead of reading `/proc` and acting on processes in
+/// this process.
+pub struct AgentClient {
+    reader: Box<dyn BufRead + Send>,
+    writer: Box<dyn Write + Send>,
+    // Fetched with the first snapshot, connections only used for control never need it
+    history: Option<History>,
+    latest_timestamp: Option<SystemTime>,
+    read_sockets: bool,
+}
+
+impl AgentClient {
+    pub fn connect<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let stream = UnixStream::connect(path).map_err(|err| match err.kind() {
+            ErrorKind::NotFound | ErrorKind::ConnectionRefused => {
+                anyhow!("No agent is listening on {}", path.display())
+            }
+            _ => anyhow!("Could not connect to {}: {}", path.display(), err),
+        })?;
+        check_peer_is_own_user(&stream, path)?;
+        Self::handshake(
+            Box::new(BufReader::new(stream.try_clone()?)),
+            Box::new(stream),
+            None,
+        )
+    }
+    /// Connects to an agent listening on TCP, e.g. `host:7878`. The token is sent in
+    /// clear text, see [`Request`].
+    pub fn connect_tcp(address: &str, token: &str) -> Result<Self> {
+        let socket_address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| anyhow!("Could not resolve {}", address))?;
+        let stream = TcpStream::connect_timeout(&socket_address, TCP_TIMEOUT)
+            .map_err(|err| anyhow!("Could not connect to {}: {}", address, err))?;
+        stream.set_nodelay(true)?;
+        // A stalled host should surface as an error instead of hanging its sampler
+        stream.set_read_timeout(Some(TCP_TIMEOUT))?;
+        Self::handshake(
+            Box::new(BufReader::new(stream.try_clone()?)),
+            Box::new(stream),
+            Some(token.to_string()),
+        )
+    }
+    fn handshake(
+        reader: Box<dyn BufRead + Send>,
+        writer: Box<dyn Write + Send>,
+        token: Option<String>,
+    ) -> Result<Self> {
+        let mut client = Self {
+            reader,
+            writer,
+            history: None,
+            latest_timestamp: None,
+            read_sockets: false,
+        };
+        client.request(&Request::Hello {
+            version: AGENT_PROTOCOL_VERSION,
+            token,
+        })?;
+        Ok(client)
+    }
+    fn request(&mut self, request: &Request) -> Result<Response> {
+        write_message(&mut self.writer, request)?;
+        let mut line = String::new();
+        if let Err(err) = read_response_line(&mut self.reader, &mut line, MAX_RESPONSE_LINE) {
+            // The rest of an overlong line can not be told apart from the next response
+            self.reader = Box::new(io::empty());
+            return Err(err);
+        }
+        match serde_json::from_str(&line)? {
+            Response::Error { message } => Err(anyhow!(message)),
+            response => Ok(response),
+        }
+    }
+    fn expect_ok(&mut self, request: &Request) -> Result<()> {
+        match self.request(request)? {
+            Response::Ok => Ok(()),
+            _ => Err(anyhow!("Unexpected response from the agent")),
+        }
+    }
+    /// Latest snapshot of the agent. Its history is kept by the client like a local
+    /// refresh would, adding each snapshot once. The first snapshot also fetches the
+    /// agent's history, so charts start out filled.
+    pub fn snapshot(&mut self) -> Result<System> {
+        if self.history.is_none() {
+            let history = self.get_history(None)?;
+            self.latest_timestamp = history
+                .get_system_samples()
+                .back()
+                .map(|sample| sample.get_timestamp());
+            self.history = Some(history);
+        }
+        if self.read_sockets {
+            self.expect_ok(&Request::ReadSockets)?;
+        }
+        let (mut system, timestamp) = match self.request(&Request::Snapshot)? {
+            Response::Snapshot { system, timestamp } => (*system, timestamp),
+            _ => return Err(anyhow!("Unexpected response from the agent")),
+        };
+        system.history = self.history.take().unwrap_or_default();
+        if self.latest_timestamp != Some(timestamp) {
+            system.record_history(timestamp);
+            self.latest_timestamp = Some(timestamp);
+        }
+        self.history = Some(system.history.clone());
+        Ok(system)
+    }
+    // The history so far, afterwards snapshots only carry the samples of their refresh
+    pub(crate) fn take_history(&mut self) -> History {
+        self.history
+            .replace(History::new(Duration::ZERO))
+            .unwrap_or_default()
+    }
+    /// Asks the agent for sockets with every snapshot, it stops reading them once no
+    /// client asked for a while.
+    pub fn set_read_sockets(&mut self, read_sockets: bool) {
+        self.read_sockets = read_sockets;
+    }
+    /// System samples and the samples of `pid`, or of every process when `pid` is `None`.
+    pub fn get_history(&mut self, pid: Option<u32>) -> Result<History> {
+        match self.request(&Request::History { pid })? {
+            Response::History { history } => Ok(history),
+            _ => Err(anyhow!("Unexpected response from the agent")),
+        }
+    }
+    pub fn get_proc_open_files(&mut self, pid: u32) -> Result<Vec<OpenFile>> {
+        match self.request(&Request::OpenFiles { pid })? {
+            Response::OpenFiles { open_files } => Ok(open_files),
+            _ => Err(anyhow!("Unexpected response from the agent")),
+        }
+    }
+    pub fn get_io_priority(&mut self, pid: u32) -> Result<IoPriority> {
+        match self.request(&Request::IoPriority { pid })? {
+            Response::IoPriority { io_priority } => Ok(io_priority),
+            _ => Err(anyhow!("Unexpected response from the agent")),
+        }
+    }
+    pub fn send_signal(&mut self, pid: u32, signal: Signal) -> Result<()> {
+        self.expect_ok(&Request::Signal {
+            pid,
+            signal: signal.as_str().to_string(),
+        })
+    }
+    pub fn set_nice(&mut self, pid: u32, nice: i32) -> Result<()> {
+        self.expect_ok(&Request::SetNice { pid, nice })
+    }
+    pub fn set_io_priority(&mut self, pid: u32, io_priority: IoPriority) -> Result<()> {
+        self.expect_ok(&Request::SetIoPriority { pid, io_priority })
+    }
+    pub fn set_scheduling_policy(
+        &mut self,
+        pid: u32,
+        policy: SchedulingPolicy,
+        rt_priority: u32,
+    ) -> Result<()> {
+        self.expect_ok(&Request::SetSchedulingPolicy {
+            pid,
+            policy,
+            rt_priority,
+        })
+    }
+    pub fn set_cpu_affinity(&mut self, pid: u32, cpus: &[usize]) -> Result<()> {
+        self.expect_ok(&Request::SetCpuAffinity {
+            pid,
+            cpus: cpus.to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use std::io::Cursor;
+
+    fn tcp_access() -> ClientAccess {
+        ClientAccess {
+            token: Some(Arc::from("secret")),
+            allow_control: false,
+        }
+    }
+
+    fn fixture_state(fixture: &Fixture) -> Mutex<AgentState> {
+        fixture.write_procs(3).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        Mutex::new(AgentState::new(system, Duration::from_secs(60)))
+    }
+
+    fn serve_input(input: String, access: ClientAccess) -> Vec<Response> {
+        let fixture = Fixture::new().unwrap();
+        let state = fixture_state(&fixture);
+        let mut output: Vec<u8> = Vec::new();
+        serve_client(Cursor::new(input), &mut output, &state, &access, || Ok(())).unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Response>(line).unwrap())
+            .collect()
+    }
+
+    fn serve(requests: &[&str], access: ClientAccess) -> Vec<Response> {
+        serve_input(requests.join("\n") + "\n", access)
+    }
+
+    fn error_message(response: &Response) -> &str {
+        match response {
+            Response::Error { message } => message,
+            _ => panic!("expected an error response"),
+        }
+    }
+
+    #[test]
+    fn sockets_are_bound_private_to_their_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let fixture = Fixture::new().unwrap();
+        let path = fixture.get_root().join("agent.sock");
+        let _listener = bind_private(&path).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o077, 0);
+    }
+
+    #[test]
+    fn agents_of_this_user_are_accepted() {
+        let (stream, _peer) = UnixStream::pair().unwrap();
+        check_peer_is_own_user(&stream, Path::new("pair")).unwrap();
+    }
+
+    #[test]
+    fn overlong_responses_are_refused() {
+        let mut line = String::new();
+        let mut reader = Cursor::new("\"ok\"\n\"ok\"\n");
+        read_response_line(&mut reader, &mut line, 5).unwrap();
+        assert_eq!(line, "\"ok\"\n");
+        line.clear();
+        let err = read_response_line(&mut reader, &mut line, 4).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The agent sent a response longer than 4 bytes"
+        );
+        let err = read_response_line(&mut Cursor::new(""), &mut line, 4).unwrap_err();
+        assert_eq!(err.to_string(), "The agent closed the connection");
+    }
+
+    #[test]
+    fn requests_before_hello_close_the_connection() {
+        let responses = serve(&[r#""snapshot""#, r#""snapshot""#], UNIX_CLIENT_ACCESS);
+        assert_eq!(responses.len(), 1);
+        assert_eq!(
+            error_message(&responses[0]),
+            "Expected a hello request first"
+        );
+    }
+
+    #[test]
+    fn other_protocol_versions_are_refused() {
+        let responses = serve(&[r#"{"hello":{"version":999}}"#], UNIX_CLIENT_ACCESS);
+        assert!(error_message(&responses[0]).starts_with("Unsupported protocol version 999"));
+    }
+
+    #[test]
+    fn tcp_clients_need_the_token() {
+        for hello in [
+            r#"{"hello":{"version":1}}"#,
+            r#"{"hello":{"version":1,"token":"secreT"}}"#,
+            r#"{"hello":{"version":1,"token":"secr"}}"#,
+            r#"{"hello":{"version":1,"token":"secret\u0000"}}"#,
+        ] {
+            let responses = serve(&[hello, r#""snapshot""#], tcp_access());
+            assert_eq!(responses.len(), 1);
+            assert_eq!(error_message(&responses[0]), "Invalid token");
+        }
+    }
+
+    #[test]
+    fn tcp_clients_only_read_without_allow_control() {
+        let responses = serve(
+            &[
+                r#"{"hello":{"version":1,"token":"secret"}}"#,
+                r#"{"signal":{"pid":1,"signal":"SIGCONT"}}"#,
+                r#"{"set_nice":{"pid":1,"nice":5}}"#,
+                r#""snapshot""#,
+            ],
+            tcp_access(),
+        );
+        assert!(matches!(responses[0], Response::Hello { version: 1 }));
+        assert!(error_message(&responses[1]).contains("--allow-control"));
+        assert!(error_message(&responses[2]).contains("--allow-control"));
+        assert!(matches!(&responses[3], Response::Snapshot { system, .. }
+            if system.get_proc_info(&2).is_some()));
+    }
+
+    #[test]
+    fn overlong_requests_close_the_connection() {
+        let input = format!(
+            "{{\"hello\":{{\"version\":1,\"token\":\"{}\"}}}}\n\"snapshot\"\n",
+            "x".repeat(MAX_REQUEST_LINE as usize)
+        );
+        let responses = serve_input(input, tcp_access());
+        assert_eq!(responses.len(), 1);
+        assert!(error_message(&responses[0]).starts_with("Requests may not be longer"));
+    }
+
+    #[test]
+    fn only_a_greeting_ends_the_hello_timeout() {
+        let fixture = Fixture::new().unwrap();
+        let state = fixture_state(&fixture);
+        let mut greetings = 0;
+        let input = "{\"hello\":{\"version\":1}}\n\"snapshot\"\n";
+        serve_client(
+            Cursor::new(input),
+            io::sink(),
+            &state,
+            &UNIX_CLIENT_ACCESS,
+            || {
+                greetings += 1;
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(greetings, 1);
+    }
+
+    #[test]
+    fn clients_beyond_the_limit_get_no_slot() {
+        let clients = Arc::new(AtomicUsize::new(0));
+        let slots: Vec<ClientSlot> = (0..MAX_CLIENTS)
+            .map(|_| ClientSlot::acquire(&clients).unwrap())
+            .collect();
+        assert!(ClientSlot::acquire(&clients).is_none());
+        drop(slots);
+        assert_eq!(clients.load(Ordering::SeqCst), 0);
+        assert!(ClientSlot::acquire(&clients).is_some());
+    }
+
+    #[test]
+    fn processes_of_a_captured_root_are_not_controlled() {
+        let responses = serve(
+            &[
+                r#"{"hello":{"version":1}}"#,
+                r#"{"signal":{"pid":1,"signal":"SIGCONT"}}"#,
+                r#"{"io_priority":{"pid":1}}"#,
+            ],
+            UNIX_CLIENT_ACCESS,
+        );
+        assert!(error_message(&responses[1]).ends_with("its processes can not be controlled"));
+        assert!(error_message(&responses[2]).ends_with("its processes can not be controlled"));
+    }
+
+    #[test]
+    fn tcp_clients_read_io_priorities_without_allow_control() {
+        let responses = serve(
+            &[
+                r#"{"hello":{"version":1,"token":"secret"}}"#,
+                r#"{"io_priority":{"pid":1}}"#,
+            ],
+            tcp_access(),
+        );
+        // Refused for the captured fixture root, not for the missing --allow-control
+        assert!(error_message(&responses[1]).ends_with("its processes can not be controlled"));
+    }
+}
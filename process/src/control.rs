@@ -0,0 +1,289 @@
+use crate::sched::{IoPriority, SchedulingPolicy};
+use anyhow::{anyhow, Error, Result};
+use nix::errno::Errno;
+use nix::libc;
+use nix::sched::{sched_setaffinity, CpuSet};
+use nix::sys::signal;
+pub use nix::sys::signal::Signal;
+use nix::unistd::Pid;
+use std::fs;
+use std::path::Path;
+
+// `which` argument of ioprio_get(2) and ioprio_set(2) selecting the single thread `who`
+const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
+fn control_error(err: Errno, action: String, pid: u32) -> Error {
+    match err {
+        Errno::EPERM | Errno::EACCES => anyhow!("Permission denied trying to {}", action),
+        Errno::ESRCH => anyhow!("PID {} no longer exists", pid),
+        err => anyhow!("Could not {}: {}", action, err),
+    }
+}
+
+// kill(2) and friends treat 0 and negative PIDs as process groups, never target those here
+fn to_raw_pid(pid: u32) -> Result<libc::pid_t> {
+    match libc::pid_t::try_from(pid) {
+        Ok(raw_pid) if raw_pid > 0 => Ok(raw_pid),
+        _ => Err(anyhow!("Invalid PID {}", pid)),
+    }
+}
+
+// Thread ids of `raw_pid`, only the thread group leader when its tasks can not be listed
+fn get_thread_ids(root: &Path, raw_pid: libc::pid_t) -> Vec<libc::pid_t> {
+    let Ok(entries) = fs::read_dir(root.join("proc").join(raw_pid.to_string()).join("task")) else {
+        return vec![raw_pid];
+    };
+    let mut tids: Vec<libc::pid_t> = entries
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    tids.sort_unstable();
+    tids
+}
+
+// Nice values, I/O priorities, scheduling policies and CPU affinities belong to threads, so
+// they are changed for every thread of `pid`, like `taskset -a` does. Threads started later
+// inherit them.
+fn for_each_thread<F>(pid: u32, action: String, apply: F) -> Result<()>
+where
+    F: FnMut(libc::pid_t) -> Result<(), Errno>,
+{
+    for_each_thread_in(Path::new("/"), pid, action, apply)
+}
+
+fn for_each_thread_in<F>(root: &Path, pid: u32, action: String, mut apply: F) -> Result<()>
+where
+    F: FnMut(libc::pid_t) -> Result<(), Errno>,
+{
+    let raw_pid = to_raw_pid(pid)?;
+    for tid in get_thread_ids(root, raw_pid) {
+        match apply(tid) {
+            // Threads may exit while they are walked
+            Err(Errno::ESRCH) if tid != raw_pid => {}
+            Err(err) => return Err(control_error(err, action, pid)),
+            Ok(()) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Sends `signal` to `pid`, turning the common failures into readable errors.
+pub fn send_signal(pid: u32, signal: Signal) -> Result<()> {
+    let raw_pid = to_raw_pid(pid)?;
+    signal::kill(Pid::from_raw(raw_pid), signal)
+        .map_err(|err| control_error(err, format!("send {} to PID {}", signal, pid), pid))
+}
+
+pub fn terminate(pid: u32) -> Result<()> {
+    send_signal(pid, Signal::SIGTERM)
+}
+
+pub fn kill(pid: u32) -> Result<()> {
+    send_signal(pid, Signal::SIGKILL)
+}
+
+pub fn stop(pid: u32) -> Result<()> {
+    send_signal(pid, Signal::SIGSTOP)
+}
+
+/// Continues a process stopped with [`stop`].
+pub fn resume(pid: u32) -> Result<()> {
+    send_signal(pid, Signal::SIGCONT)
+}
+
+/// Sets the nice value of every thread of `pid`. Lowering it below its current value
+/// needs CAP_SYS_NICE.
+pub fn set_nice(pid: u32, nice: i32) -> Result<()> {
+    for_each_thread(pid, format!("set nice {} on PID {}", nice, pid), |tid| {
+        // SAFETY: setpriority only reads its integer arguments
+        let result = unsafe { libc::setpriority(libc::PRIO_PROCESS, tid as libc::id_t, nice) };
+        Errno::result(result).map(drop)
+    })
+}
+
+/// I/O priorities are not exposed in procfs, so this always queries the running kernel
+/// for the thread group leader of `pid`.
+pub fn get_io_priority(pid: u32) -> Result<IoPriority> {
+    let raw_pid = to_raw_pid(pid)?;
+    // SAFETY: ioprio_get only reads its integer arguments
+    let result = unsafe { libc::syscall(libc::SYS_ioprio_get, IOPRIO_WHO_PROCESS, raw_pid) };
+    Errno::result(result)
+        .map(|io_priority| IoPriority::from_raw(io_priority as i32))
+        .map_err(|err| control_error(err, format!("read the I/O priority of PID {}", pid), pid))
+}
+
+/// Sets the I/O priority of every thread of `pid`.
+pub fn set_io_priority(pid: u32, io_priority: IoPriority) -> Result<()> {
+    let action = format!(
+        "set I/O priority {} {} on PID {}",
+        io_priority.get_class().as_str(),
+        io_priority.get_level(),
+        pid
+    );
+    for_each_thread(pid, action, |tid| {
+        // SAFETY: ioprio_set only reads its integer arguments
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                tid,
+                io_priority.as_raw(),
+            )
+        };
+        Errno::result(result).map(drop)
+    })
+}
+
+/// Sets the policy of every thread of `pid`. `rt_priority` must be between 1 and 99 for
+/// real-time policies and is ignored otherwise.
+pub fn set_scheduling_policy(pid: u32, policy: SchedulingPolicy, rt_priority: u32) -> Result<()> {
+    let raw_policy = policy
+        .as_raw()
+        .ok_or_else(|| anyhow!("Setting {} is not supported", policy.as_str()))?;
+    if policy.is_realtime() && !(1..=99).contains(&rt_priority) {
+        return Err(anyhow!(
+            "Real-time priority {} is not between 1 and 99",
+            rt_priority
+        ));
+    }
+    let sched_param = libc::sched_param {
+        sched_priority: match policy.is_realtime() {
+            true => rt_priority as libc::c_int,
+            false => 0,
+        },
+    };
+    let action = format!("set {} on PID {}", policy.as_str(), pid);
+    for_each_thread(pid, action, |tid| {
+        // SAFETY: sched_param outlives the call and is only read by the kernel
+        let result = unsafe { libc::sched_setscheduler(tid, raw_policy, &sched_param) };
+        Errno::result(result).map(drop)
+    })
+}
+
+/// Sets the CPU affinity of every thread of `pid`.
+pub fn set_cpu_affinity(pid: u32, cpus: &[usize]) -> Result<()> {
+    if cpus.is_empty() {
+        return Err(anyhow!("At least one CPU must be allowed for PID {}", pid));
+    }
+    let mut cpu_set = CpuSet::new();
+    for cpu in cpus {
+        cpu_set
+            .set(*cpu)
+            .map_err(|_| anyhow!("CPU {} is out of range", cpu))?;
+    }
+    let action = format!("set the CPU affinity of PID {}", pid);
+    for_each_thread(pid, action, |tid| {
+        sched_setaffinity(Pid::from_raw(tid), &cpu_set)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, FixtureProc};
+    use std::process::Command;
+
+    #[test]
+    fn realtime_priorities_out_of_range_are_refused() {
+        let err = set_scheduling_policy(1, SchedulingPolicy::Fifo, 100).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Real-time priority 100 is not between 1 and 99"
+        );
+    }
+
+    #[test]
+    fn every_thread_is_visited() {
+        let fixture = Fixture::new().unwrap();
+        let mut proc = FixtureProc::new(42, 1);
+        proc.threads = 4;
+        fixture.write_proc(&proc).unwrap();
+        let tids: Vec<libc::pid_t> = proc
+            .get_tids()
+            .iter()
+            .map(|tid| *tid as libc::pid_t)
+            .collect();
+        let mut visited: Vec<libc::pid_t> = Vec::new();
+        for_each_thread_in(fixture.get_root(), 42, String::from("test"), |tid| {
+            visited.push(tid);
+            // A thread exiting while the threads are walked is not an error
+            match tid == tids[2] {
+                true => Err(Errno::ESRCH),
+                false => Ok(()),
+            }
+        })
+        .unwrap();
+        assert_eq!(visited, tids);
+        let err = for_each_thread_in(fixture.get_root(), 42, String::from("test"), |tid| {
+            match tid == 42 {
+                true => Err(Errno::ESRCH),
+                false => Ok(()),
+            }
+        })
+        .unwrap_err();
+        assert_eq!(err.to_string(), "PID 42 no longer exists");
+        // Without a readable task directory only the thread group leader is changed
+        visited.clear();
+        for_each_thread_in(fixture.get_root(), 43, String::from("test"), |tid| {
+            visited.push(tid);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(visited, vec![43]);
+    }
+
+    #[test]
+    fn nice_is_set_on_every_thread() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let result = set_nice(child.id(), 5);
+        let nices: Vec<String> = get_thread_ids(Path::new("/"), child.id() as libc::pid_t)
+            .into_iter()
+            .map(|tid| {
+                let stat =
+                    fs::read_to_string(format!("/proc/{}/task/{}/stat", child.id(), tid)).unwrap();
+                // Index 16 after the comm is field 19 (nice) of proc(5)
+                stat[stat.rfind(')').unwrap() + 1..]
+                    .split_whitespace()
+                    .nth(16)
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        child.kill().unwrap();
+        child.wait().unwrap();
+        result.unwrap();
+        assert!(!nices.is_empty());
+        assert!(nices.iter().all(|nice| nice == "5"));
+    }
+
+    #[test]
+    fn affinity_is_set_on_every_thread() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let result = set_cpu_affinity(child.id(), &[0]);
+        let affinities: Vec<String> = get_thread_ids(Path::new("/"), child.id() as libc::pid_t)
+            .into_iter()
+            .map(|tid| {
+                fs::read_to_string(format!("/proc/{}/task/{}/status", child.id(), tid))
+                    .unwrap()
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Cpus_allowed_list:"))
+                    .unwrap()
+                    .trim()
+                    .to_string()
+            })
+            .collect();
+        child.kill().unwrap();
+        child.wait().unwrap();
+        result.unwrap();
+        assert!(!affinities.is_empty());
+        assert!(affinities.iter().all(|cpus| cpus == "0"));
+    }
+
+    #[test]
+    fn exited_processes_are_reported() {
+        let mut child = Command::new("true").spawn().unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        let err = set_nice(pid, 5).unwrap_err();
+        assert_eq!(err.to_string(), format!("PID {} no longer exists", pid));
+    }
+}
@@ -0,0 +1,266 @@
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, Default)]
+pub(crate) struct CpuTimes {
+    user: u64,
+    nice: u64,
+    system: u64,
+    idle: u64,
+    iowait: u64,
+    irq: u64,
+    softirq: u64,
+    steal: u64,
+}
+
+impl CpuTimes {
+    fn parse(cpu_metrics: &str) -> Result<Self> {
+        // guest and guest_nice are already accounted for in user and nice
+        let mut fields = [0u64; 8];
+        for (field, cpu_metric) in fields.iter_mut().zip(cpu_metrics.split_whitespace()) {
+            *field = cpu_metric.parse::<u64>()?;
+        }
+        Ok(Self {
+            user: fields[0],
+            nice: fields[1],
+            system: fields[2],
+            idle: fields[3],
+            iowait: fields[4],
+            irq: fields[5],
+            softirq: fields[6],
+            steal: fields[7],
+        })
+    }
+    fn delta(&self, prev: &CpuTimes) -> CpuTimes {
+        CpuTimes {
+            user: self.user.saturating_sub(prev.user),
+            nice: self.nice.saturating_sub(prev.nice),
+            system: self.system.saturating_sub(prev.system),
+            idle: self.idle.saturating_sub(prev.idle),
+            iowait: self.iowait.saturating_sub(prev.iowait),
+            irq: self.irq.saturating_sub(prev.irq),
+            softirq: self.softirq.saturating_sub(prev.softirq),
+            steal: self.steal.saturating_sub(prev.steal),
+        }
+    }
+    pub(crate) fn total(&self) -> u64 {
+        self.user
+            + self.nice
+            + self.system
+            + self.idle
+            + self.iowait
+            + self.irq
+            + self.softirq
+            + self.steal
+    }
+    pub(crate) fn idle(&self) -> u64 {
+        self.idle + self.iowait
+    }
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuCoreUsage {
+    id: usize,
+    total: f32,
+    user: f32,
+    system: f32,
+    iowait: f32,
+    steal: f32,
+    irq: f32,
+}
+
+impl CpuCoreUsage {
+    pub(crate) fn new(id: usize, times: &CpuTimes, prev_times: Option<&CpuTimes>) -> Self {
+        let times = match prev_times {
+            Some(prev_times) => times.delta(prev_times),
+            None => *times,
+        };
+        let total_time = times.total();
+        if total_time == 0 {
+            return Self {
+                id,
+                ..Default::default()
+            };
+        }
+        let percent = |time: u64| time as f32 * 100f32 / total_time as f32;
+        Self {
+            id,
+            total: 100f32 - percent(times.idle()),
+            user: percent(times.user + times.nice),
+            system: percent(times.system),
+            iowait: percent(times.iowait),
+            steal: percent(times.steal),
+            irq: percent(times.irq + times.softirq),
+        }
+    }
+    pub fn get_id(&self) -> usize {
+        self.id
+    }
+    pub fn get_total_usage(&self) -> f32 {
+        self.total
+    }
+    pub fn get_user(&self) -> f32 {
+        self.user
+    }
+    pub fn get_system(&self) -> f32 {
+        self.system
+    }
+    pub fn get_iowait(&self) -> f32 {
+        self.iowait
+    }
+    pub fn get_steal(&self) -> f32 {
+        self.steal
+    }
+    pub fn get_irq(&self) -> f32 {
+        self.irq
+    }
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CpuTopology {
+    sockets: usize,
+    cores: usize,
+    threads: usize,
+    smt_siblings: Vec<Vec<usize>>,
+}
+
+impl CpuTopology {
+    pub fn get_sockets(&self) -> usize {
+        self.sockets
+    }
+    pub fn get_cores(&self) -> usize {
+        self.cores
+    }
+    pub fn get_threads(&self) -> usize {
+        self.threads
+    }
+    /// Groups of logical CPUs sharing one physical core.
+    pub fn get_smt_siblings(&self) -> &Vec<Vec<usize>> {
+        &self.smt_siblings
+    }
+}
+
+/// Parses a kernel CPU list such as `0-3,8`, e.g. `Cpus_allowed_list` or `online`.
+pub fn parse_cpu_list(cpu_list: &str) -> Result<Vec<usize>> {
+    let mut cpus: Vec<usize> = Vec::new();
+    for range in cpu_list.trim().split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => cpus.extend(first.parse::<usize>()?..=last.parse::<usize>()?),
+            None => cpus.push(range.parse::<usize>()?),
+        }
+    }
+    Ok(cpus)
+}
+
+/// Returns the aggregate `cpu` line and the per-core `cpuN` lines of `/proc/stat`.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_cpu_times(root: &Path) -> Result<(CpuTimes, HashMap<usize, CpuTimes>)> {
+    let mut total_times = CpuTimes::default();
+    let mut core_times: HashMap<usize, CpuTimes> = HashMap::new();
+    let buffer = fs::read_to_string(root.join("proc/stat"))?;
+    for line in buffer.lines().filter(|line| line.starts_with("cpu")) {
+        let Some((cpu_name, cpu_metrics)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        match cpu_name.strip_prefix("cpu").unwrap_or_default() {
+            "" => total_times = CpuTimes::parse(cpu_metrics)?,
+            core_id => {
+                core_times.insert(core_id.parse::<usize>()?, CpuTimes::parse(cpu_metrics)?);
+            }
+        }
+    }
+    Ok((total_times, core_times))
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn get_cpu_topology(root: &Path, num_of_cpus: usize) -> CpuTopology {
+    let mut packages: BTreeSet<String> = BTreeSet::new();
+    let mut cores: BTreeSet<(String, String)> = BTreeSet::new();
+    let mut smt_siblings: BTreeSet<Vec<usize>> = BTreeSet::new();
+    let mut threads = 0usize;
+    if let Ok(cpu_dirs) = fs::read_dir(root.join("sys/devices/system/cpu")) {
+        for cpu_dir in cpu_dirs.flatten() {
+            let file_name = cpu_dir.file_name();
+            let Some(cpu_id) = file_name.to_str().and_then(|name| name.strip_prefix("cpu")) else {
+                continue;
+            };
+            if cpu_id.is_empty() || !cpu_id.chars().all(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let topology = cpu_dir.path().join("topology");
+            // Offline CPUs have no topology directory
+            let (Ok(package_id), Ok(core_id)) = (
+                fs::read_to_string(topology.join("physical_package_id")),
+                fs::read_to_string(topology.join("core_id")),
+            ) else {
+                continue;
+            };
+            threads += 1;
+            packages.insert(package_id.trim().to_string());
+            cores.insert((package_id.trim().to_string(), core_id.trim().to_string()));
+            if let Ok(siblings) = fs::read_to_string(topology.join("thread_siblings_list")) {
+                if let Ok(siblings) = parse_cpu_list(&siblings) {
+                    smt_siblings.insert(siblings);
+                }
+            }
+        }
+    }
+    if threads == 0 {
+        // No sysfs available (e.g. a procfs-only snapshot), only the logical count is known
+        return CpuTopology {
+            threads: num_of_cpus,
+            ..Default::default()
+        };
+    }
+    CpuTopology {
+        sockets: packages.len(),
+        cores: cores.len(),
+        threads,
+        smt_siblings: smt_siblings.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn cpu_list_ranges_and_singles() {
+        assert_eq!(
+            parse_cpu_list("0-2,5,7-8\n").unwrap(),
+            vec![0, 1, 2, 5, 7, 8]
+        );
+        assert_eq!(parse_cpu_list("3").unwrap(), vec![3]);
+        assert!(parse_cpu_list("").unwrap().is_empty());
+        assert!(parse_cpu_list("0-x").is_err());
+    }
+
+    #[test]
+    fn cpu_times_are_split_into_total_and_cores() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        let (total_times, core_times) = get_cpu_times(fixture.get_root()).unwrap();
+        assert_eq!(total_times.total(), 96150);
+        assert_eq!(total_times.idle(), 90100);
+        let mut core_ids: Vec<usize> = core_times.keys().copied().collect();
+        core_ids.sort_unstable();
+        assert_eq!(core_ids, vec![0, 1, 2, 3]);
+        assert_eq!(core_times[&1].total(), 24038);
+    }
+
+    #[test]
+    fn core_usage_over_an_interval() {
+        let prev_times = CpuTimes::parse("100 0 100 800 0 0 0 0").unwrap();
+        let times = CpuTimes::parse("160 0 120 820 0 0 0 0").unwrap();
+        let core_usage = CpuCoreUsage::new(2, &times, Some(&prev_times));
+        assert_eq!(core_usage.get_id(), 2);
+        assert_eq!(core_usage.get_total_usage(), 80f32);
+        assert_eq!(core_usage.get_user(), 60f32);
+        assert_eq!(core_usage.get_system(), 20f32);
+    }
+}
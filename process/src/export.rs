@@ -0,0 +1,229 @@
+use crate::{Process, ProcessTree};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "json" => Ok(ExportFormat::Json),
+            "csv" => Ok(ExportFormat::Csv),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            _ => Err(anyhow!(
+                "Unknown export format {}, expected json, csv or ndjson",
+                format
+            )),
+        }
+    }
+}
+
+// One flat row per process, as written by the CSV and NDJSON exporters
+#[derive(Serialize)]
+struct ProcessRecord<'a> {
+    pid: u32,
+    ppid: u32,
+    name: &'a str,
+    user: &'a str,
+    path: &'a str,
+    cpu_used: f32,
+    mem_used: f32,
+    rss: u64,
+    pss: u64,
+    uss: u64,
+    shared: u64,
+    swap: u64,
+    threads: usize,
+    priority: i64,
+    nice: i64,
+    policy: &'static str,
+    processor: u32,
+    read_bytes: u64,
+    write_bytes: u64,
+    read_rate: f32,
+    write_rate: f32,
+}
+
+impl<'a> ProcessRecord<'a> {
+    fn new(proc_info: &'a Process) -> Self {
+        Self {
+            pid: proc_info.pid,
+            ppid: proc_info.ppid,
+            name: &proc_info.name,
+            user: &proc_info.user,
+            path: &proc_info.path,
+            cpu_used: proc_info.cpu_used,
+            mem_used: proc_info.mem_used,
+            rss: proc_info.memory.get_rss(),
+            pss: proc_info.memory.get_pss(),
+            uss: proc_info.memory.get_uss(),
+            shared: proc_info.memory.get_shared(),
+            swap: proc_info.memory.get_swap(),
+            threads: proc_info.threads.len(),
+            priority: proc_info.scheduling.get_priority(),
+            nice: proc_info.scheduling.get_nice(),
+            policy: proc_info.scheduling.get_policy().as_str(),
+            processor: proc_info.processor,
+            read_bytes: proc_info.io.get_read_bytes(),
+            write_bytes: proc_info.io.get_write_bytes(),
+            read_rate: proc_info.io.get_read_rate(),
+            write_rate: proc_info.io.get_write_rate(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProcessSampleRecord<'a> {
+    // Seconds since the Unix epoch
+    timestamp: f64,
+    #[serde(flatten)]
+    record: ProcessRecord<'a>,
+}
+
+/// Whole tree, including threads and sockets of every process.
+pub fn export_json<W: Write>(proc_tree: &ProcessTree, writer: W) -> Result<()> {
+    serde_json::to_writer_pretty(writer, proc_tree)?;
+    Ok(())
+}
+
+pub fn export_csv<'a, W, I>(procs: I, writer: W) -> Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a Process>,
+{
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    for proc_info in procs {
+        csv_writer.serialize(ProcessRecord::new(proc_info))?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Appends one line per process, all stamped with the time of the sample.
+pub fn export_ndjson<'a, W, I>(procs: I, timestamp: SystemTime, mut writer: W) -> Result<()>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a Process>,
+{
+    let timestamp = timestamp.duration_since(UNIX_EPOCH)?.as_secs_f64();
+    for proc_info in procs {
+        serde_json::to_writer(
+            &mut writer,
+            &ProcessSampleRecord {
+                timestamp,
+                record: ProcessRecord::new(proc_info),
+            },
+        )?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use crate::{build_process_tree, ProcessTreeNode, System};
+    use std::time::Duration;
+
+    fn fixture_system(procs: u32) -> System {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(procs).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.set_read_sockets(true);
+        system.refresh_system_info().unwrap();
+        system
+    }
+
+    fn sorted_procs(system: &mut System) -> Vec<Process> {
+        let mut proc_list = system.get_procs_as_list();
+        proc_list.sort_unstable_by_key(|(pid, _)| *pid);
+        proc_list
+            .into_iter()
+            .map(|(_, proc_info)| proc_info)
+            .collect()
+    }
+
+    fn tree_pids(node: &ProcessTreeNode, pids: &mut Vec<u32>) {
+        pids.push(node.proc_info.get_pid());
+        for child in node.children.iter() {
+            tree_pids(child, pids);
+        }
+    }
+
+    #[test]
+    fn csv_has_a_header_and_a_row_per_process() {
+        let procs = sorted_procs(&mut fixture_system(5));
+        let mut output: Vec<u8> = Vec::new();
+        export_csv(&procs, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines[0],
+            "pid,ppid,name,user,path,cpu_used,mem_used,rss,pss,uss,shared,swap,threads,\
+             priority,nice,policy,processor,read_bytes,write_bytes,read_rate,write_rate"
+        );
+        assert_eq!(lines.len(), 6);
+        assert!(lines[3].starts_with("3,1,worker 3,user3,/usr/bin/worker,"));
+    }
+
+    #[test]
+    fn json_round_trips_through_serde() {
+        let proc_tree = build_process_tree(&fixture_system(5));
+        let mut output: Vec<u8> = Vec::new();
+        export_json(&proc_tree, &mut output).unwrap();
+        let parsed: ProcessTree = serde_json::from_slice(&output).unwrap();
+        let (mut pids, mut parsed_pids) = (Vec::new(), Vec::new());
+        tree_pids(&proc_tree.root, &mut pids);
+        tree_pids(&parsed.root, &mut parsed_pids);
+        assert_eq!(parsed_pids, pids);
+        let worker = &parsed.root.children[0].children[1].proc_info;
+        assert_eq!(worker.get_pid(), 3);
+        assert_eq!(worker.get_user(), "user3");
+        assert_eq!(worker.get_threads().len(), 4);
+        assert_eq!(worker.get_sockets()[0].get_inode(), 100_003);
+        assert_eq!(worker.get_memory().get_pss(), 2560 * 1024);
+    }
+
+    #[test]
+    fn ndjson_writes_one_object_per_line() {
+        let procs = sorted_procs(&mut fixture_system(3));
+        let mut output: Vec<u8> = Vec::new();
+        let timestamp = UNIX_EPOCH + Duration::from_millis(1_500);
+        export_ndjson(&procs, timestamp, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with('\n'));
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        for (line, proc_info) in lines.iter().zip(procs.iter()) {
+            assert!(line.is_object());
+            assert_eq!(line["timestamp"], 1.5);
+            assert_eq!(line["pid"], proc_info.get_pid());
+            assert_eq!(line["name"], proc_info.get_name().as_str());
+        }
+    }
+}
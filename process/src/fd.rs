@@ -0,0 +1,174 @@
+use anyhow::Result;
+use nix::fcntl::OFlag;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FileDescriptorKind {
+    File,
+    Socket,
+    Pipe,
+    AnonInode,
+    Other,
+}
+
+impl FileDescriptorKind {
+    fn from_target(target: &str) -> Self {
+        if target.starts_with('/') {
+            FileDescriptorKind::File
+        } else if target.starts_with("socket:[") {
+            FileDescriptorKind::Socket
+        } else if target.starts_with("pipe:[") {
+            FileDescriptorKind::Pipe
+        } else if target.starts_with("anon_inode:") {
+            FileDescriptorKind::AnonInode
+        } else {
+            FileDescriptorKind::Other
+        }
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileDescriptorKind::File => "file",
+            FileDescriptorKind::Socket => "socket",
+            FileDescriptorKind::Pipe => "pipe",
+            FileDescriptorKind::AnonInode => "anon_inode",
+            FileDescriptorKind::Other => "other",
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpenFile {
+    fd: u32,
+    target: String,
+    kind: FileDescriptorKind,
+    flags: u32,
+    position: u64,
+}
+
+impl OpenFile {
+    pub fn get_fd(&self) -> u32 {
+        self.fd
+    }
+    pub fn get_target(&self) -> &String {
+        &self.target
+    }
+    pub fn get_kind(&self) -> FileDescriptorKind {
+        self.kind
+    }
+    /// Raw `open(2)` flags from `fdinfo`.
+    pub fn get_flags(&self) -> u32 {
+        self.flags
+    }
+    pub fn get_position(&self) -> u64 {
+        self.position
+    }
+    pub fn get_access_mode(&self) -> &'static str {
+        match OFlag::from_bits_truncate(self.flags as i32) & OFlag::O_ACCMODE {
+            OFlag::O_WRONLY => "w",
+            OFlag::O_RDWR => "rw",
+            _ => "r",
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn get_fd_info(root: &Path, pid: u32, fd: u32) -> Result<(u32, u64)> {
+    let buffer = fs::read_to_string(
+        root.join("proc")
+            .join(pid.to_string())
+            .join("fdinfo")
+            .join(fd.to_string()),
+    )?;
+    let mut flags = 0u32;
+    let mut position = 0u64;
+    for line in buffer.lines() {
+        match line.split_once(':') {
+            Some(("flags", value)) => flags = u32::from_str_radix(value.trim(), 8)?,
+            Some(("pos", value)) => position = value.trim().parse::<u64>()?,
+            _ => {}
+        }
+    }
+    Ok((flags, position))
+}
+
+/// Descriptors closed while the directory is being read are left out.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_open_files(root: &Path, pid: u32) -> Result<Vec<OpenFile>> {
+    let mut open_files: Vec<OpenFile> = Vec::new();
+    let fd_dir = fs::read_dir(root.join("proc").join(pid.to_string()).join("fd"))?;
+    for fd_entry in fd_dir.flatten() {
+        let Some(Ok(fd)) = fd_entry.file_name().to_str().map(str::parse::<u32>) else {
+            continue;
+        };
+        let Ok(target) = fs::read_link(fd_entry.path()) else {
+            continue;
+        };
+        let Ok((flags, position)) = get_fd_info(root, pid, fd) else {
+            continue;
+        };
+        let target = target.to_string_lossy().to_string();
+        open_files.push(OpenFile {
+            fd,
+            kind: FileDescriptorKind::from_target(&target),
+            target,
+            flags,
+            position,
+        });
+    }
+    open_files.sort_unstable_by_key(|open_file| open_file.fd);
+    Ok(open_files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, FixtureProc};
+
+    #[test]
+    fn open_files_are_read_with_their_kind_and_flags() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_proc(&FixtureProc::new(42, 1)).unwrap();
+        let open_files = get_open_files(fixture.get_root(), 42).unwrap();
+        let targets: Vec<(u32, &str, FileDescriptorKind)> = open_files
+            .iter()
+            .map(|open_file| {
+                (
+                    open_file.get_fd(),
+                    open_file.get_target().as_str(),
+                    open_file.get_kind(),
+                )
+            })
+            .collect();
+        assert!(
+            targets
+                == vec![
+                    (0, "/dev/null", FileDescriptorKind::File),
+                    (1, "/var/log/worker-42.log", FileDescriptorKind::File),
+                    (2, "pipe:[200042]", FileDescriptorKind::Pipe),
+                    (3, "socket:[100042]", FileDescriptorKind::Socket),
+                ]
+        );
+        assert_eq!(open_files[0].get_access_mode(), "r");
+        assert_eq!(open_files[1].get_access_mode(), "w");
+        assert_eq!(open_files[1].get_position(), 4096);
+        assert_eq!(open_files[3].get_access_mode(), "rw");
+    }
+
+    #[test]
+    fn descriptors_closed_while_reading_are_left_out() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_proc(&FixtureProc::new(42, 1)).unwrap();
+        // Closed after the fd directory was listed but before its fdinfo was read
+        fs::remove_file(fixture.get_root().join("proc/42/fdinfo/2")).unwrap();
+        let fds: Vec<u32> = get_open_files(fixture.get_root(), 42)
+            .unwrap()
+            .iter()
+            .map(|open_file| open_file.get_fd())
+            .collect();
+        assert_eq!(fds, vec![0, 1, 3]);
+        assert!(get_open_files(fixture.get_root(), 43).is_err());
+    }
+}
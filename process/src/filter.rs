@@ -0,0 +1,634 @@
+use crate::{Process, ProcessTree, ProcessTreeNode};
+use anyhow::{anyhow, Result};
+use regex::Regex;
+
+// Parentheses and `!` nested deeper than this are refused, as they are parsed and matched
+// recursively
+const MAX_NESTING: usize = 64;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Pid,
+    Ppid,
+    Name,
+    User,
+    Path,
+    Cpu,
+    Mem,
+    Rss,
+    Threads,
+    Priority,
+    Nice,
+    Processor,
+    ReadRate,
+    WriteRate,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pid" => Some(Field::Pid),
+            "ppid" => Some(Field::Ppid),
+            "name" => Some(Field::Name),
+            "user" => Some(Field::User),
+            "path" => Some(Field::Path),
+            "cpu" => Some(Field::Cpu),
+            "mem" => Some(Field::Mem),
+            "rss" => Some(Field::Rss),
+            "threads" => Some(Field::Threads),
+            "priority" => Some(Field::Priority),
+            "nice" => Some(Field::Nice),
+            "processor" => Some(Field::Processor),
+            "read_rate" => Some(Field::ReadRate),
+            "write_rate" => Some(Field::WriteRate),
+            _ => None,
+        }
+    }
+    fn is_text(&self) -> bool {
+        matches!(self, Field::Name | Field::User | Field::Path)
+    }
+    fn text_value<'a>(&self, proc_info: &'a Process) -> &'a str {
+        match self {
+            Field::Name => &proc_info.name,
+            Field::User => &proc_info.user,
+            _ => &proc_info.path,
+        }
+    }
+    fn number_value(&self, proc_info: &Process) -> f64 {
+        match self {
+            Field::Pid => proc_info.pid as f64,
+            Field::Ppid => proc_info.ppid as f64,
+            Field::Cpu => proc_info.cpu_used as f64,
+            Field::Mem => proc_info.mem_used as f64,
+            Field::Rss => proc_info.memory.get_rss() as f64,
+            Field::Threads => proc_info.threads.len() as f64,
+            Field::Priority => proc_info.scheduling.get_priority() as f64,
+            Field::Nice => proc_info.scheduling.get_nice() as f64,
+            Field::Processor => proc_info.processor as f64,
+            Field::ReadRate => proc_info.io.get_read_rate() as f64,
+            Field::WriteRate => proc_info.io.get_write_rate() as f64,
+            Field::Name | Field::User | Field::Path => 0f64,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Matches,
+    NotMatches,
+}
+
+impl Operator {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Equal => "==",
+            Operator::NotEqual => "!=",
+            Operator::Less => "<",
+            Operator::LessEqual => "<=",
+            Operator::Greater => ">",
+            Operator::GreaterEqual => ">=",
+            Operator::Matches => "~",
+            Operator::NotMatches => "!~",
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Value {
+    Number(f64),
+    Text(String),
+    Pattern(Regex),
+}
+
+// Chains of `&&` and `||` are kept flat, so their length does not add to the nesting
+#[derive(Clone)]
+enum Expr {
+    And(Vec<Expr>),
+    Or(Vec<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, Operator, Value),
+}
+
+impl Expr {
+    fn matches(&self, proc_info: &Process) -> bool {
+        match self {
+            Expr::And(exprs) => exprs.iter().all(|expr| expr.matches(proc_info)),
+            Expr::Or(exprs) => exprs.iter().any(|expr| expr.matches(proc_info)),
+            Expr::Not(expr) => !expr.matches(proc_info),
+            Expr::Compare(field, operator, Value::Pattern(pattern)) => {
+                let is_match = pattern.is_match(field.text_value(proc_info));
+                match operator {
+                    Operator::NotMatches => !is_match,
+                    _ => is_match,
+                }
+            }
+            Expr::Compare(field, operator, Value::Text(text)) => {
+                let is_equal = field.text_value(proc_info) == text;
+                match operator {
+                    Operator::NotEqual => !is_equal,
+                    _ => is_equal,
+                }
+            }
+            Expr::Compare(field, operator, Value::Number(number)) => {
+                let value = field.number_value(proc_info);
+                match operator {
+                    Operator::Equal => value == *number,
+                    Operator::NotEqual => value != *number,
+                    Operator::Less => value < *number,
+                    Operator::LessEqual => value <= *number,
+                    Operator::Greater => value > *number,
+                    Operator::GreaterEqual => value >= *number,
+                    Operator::Matches | Operator::NotMatches => false,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(f64),
+    Text(String),
+    Operator(Operator),
+    And,
+    Or,
+    Not,
+    LeftParen,
+    RightParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>> {
+    let mut tokens: Vec<(usize, Token)> = Vec::new();
+    let chars: Vec<(usize, char)> = input.char_indices().collect();
+    let mut index = 0;
+    while index < chars.len() {
+        let (position, current) = chars[index];
+        let next = chars.get(index + 1).map(|(_, next)| *next);
+        let (token, length) = match (current, next) {
+            (current, _) if current.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Operator(Operator::Equal), 2),
+            ('!', Some('=')) => (Token::Operator(Operator::NotEqual), 2),
+            ('!', Some('~')) => (Token::Operator(Operator::NotMatches), 2),
+            ('<', Some('=')) => (Token::Operator(Operator::LessEqual), 2),
+            ('>', Some('=')) => (Token::Operator(Operator::GreaterEqual), 2),
+            ('<', _) => (Token::Operator(Operator::Less), 1),
+            ('>', _) => (Token::Operator(Operator::Greater), 1),
+            ('~', _) => (Token::Operator(Operator::Matches), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::LeftParen, 1),
+            (')', _) => (Token::RightParen, 1),
+            ('"', _) => {
+                let mut text = String::new();
+                let mut length = 1;
+                let mut closed = false;
+                while let Some((_, text_char)) = chars.get(index + length) {
+                    length += 1;
+                    match text_char {
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        // Other escapes are kept as is for regex patterns
+                        '\\' => match chars.get(index + length) {
+                            Some((_, escaped @ ('"' | '\\'))) => {
+                                text.push(*escaped);
+                                length += 1;
+                            }
+                            _ => text.push('\\'),
+                        },
+                        _ => text.push(*text_char),
+                    }
+                }
+                if !closed {
+                    return Err(anyhow!("Unterminated string at position {}", position));
+                }
+                (Token::Text(text), length)
+            }
+            (current, _) if current.is_ascii_digit() || current == '.' || current == '-' => {
+                let length = chars[index..]
+                    .iter()
+                    .enumerate()
+                    .take_while(|(offset, (_, digit))| {
+                        digit.is_ascii_digit() || *digit == '.' || (*offset == 0 && *digit == '-')
+                    })
+                    .count();
+                let literal: String = chars[index..index + length]
+                    .iter()
+                    .map(|(_, digit)| digit)
+                    .collect();
+                let number = literal
+                    .parse::<f64>()
+                    .map_err(|_| anyhow!("Invalid number {} at position {}", literal, position))?;
+                (Token::Number(number), length)
+            }
+            (current, _) if current.is_alphanumeric() || current == '_' => {
+                let length = chars[index..]
+                    .iter()
+                    .take_while(|(_, ident)| {
+                        ident.is_alphanumeric() || matches!(ident, '_' | '-' | '.' | '/')
+                    })
+                    .count();
+                let ident: String = chars[index..index + length]
+                    .iter()
+                    .map(|(_, ident)| ident)
+                    .collect();
+                (Token::Ident(ident), length)
+            }
+            (current, _) => {
+                return Err(anyhow!(
+                    "Unexpected character '{}' at position {}",
+                    current,
+                    position
+                ))
+            }
+        };
+        tokens.push((position, token));
+        index += length;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    index: usize,
+    input_len: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|(_, token)| token)
+    }
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.index)
+            .map(|(position, _)| *position)
+            .unwrap_or(self.input_len)
+    }
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.index).map(|(_, token)| token.clone());
+        self.index += 1;
+        token
+    }
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            exprs.push(self.parse_and()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::Or(exprs),
+        })
+    }
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut exprs = vec![self.parse_unary()?];
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            exprs.push(self.parse_unary()?);
+        }
+        Ok(match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::And(exprs),
+        })
+    }
+    fn parse_unary(&mut self) -> Result<Expr> {
+        let position = self.position();
+        let token = self.next();
+        if matches!(token, Some(Token::Not | Token::LeftParen)) {
+            if self.depth == MAX_NESTING {
+                return Err(anyhow!(
+                    "Nesting deeper than {} levels at position {}",
+                    MAX_NESTING,
+                    position
+                ));
+            }
+            self.depth += 1;
+        }
+        let expr = match token {
+            Some(Token::Not) => Expr::Not(Box::new(self.parse_unary()?)),
+            Some(Token::LeftParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RightParen) => expr,
+                    _ => return Err(anyhow!("Missing ')' for '(' at position {}", position)),
+                }
+            }
+            Some(Token::Ident(name)) => return self.parse_comparison(&name, position),
+            _ => return Err(anyhow!("Expected a field name at position {}", position)),
+        };
+        self.depth -= 1;
+        Ok(expr)
+    }
+    fn parse_comparison(&mut self, name: &str, position: usize) -> Result<Expr> {
+        let field = Field::from_name(name)
+            .ok_or_else(|| anyhow!("Unknown field {} at position {}", name, position))?;
+        let operator_position = self.position();
+        let Some(Token::Operator(operator)) = self.next() else {
+            return Err(anyhow!(
+                "Expected a comparison operator at position {}",
+                operator_position
+            ));
+        };
+        let value_position = self.position();
+        let value = match (self.next(), field.is_text(), operator) {
+            (Some(Token::Text(text) | Token::Ident(text)), true, Operator::Matches)
+            | (Some(Token::Text(text) | Token::Ident(text)), true, Operator::NotMatches) => {
+                Value::Pattern(Regex::new(&text).map_err(|err| {
+                    anyhow!("Invalid pattern at position {}: {}", value_position, err)
+                })?)
+            }
+            (Some(Token::Text(text) | Token::Ident(text)), true, Operator::Equal)
+            | (Some(Token::Text(text) | Token::Ident(text)), true, Operator::NotEqual) => {
+                Value::Text(text)
+            }
+            (Some(Token::Number(number)), true, Operator::Equal)
+            | (Some(Token::Number(number)), true, Operator::NotEqual) => {
+                Value::Text(number.to_string())
+            }
+            (Some(Token::Number(number)), false, operator)
+                if !matches!(operator, Operator::Matches | Operator::NotMatches) =>
+            {
+                Value::Number(number)
+            }
+            (_, true, Operator::Less)
+            | (_, true, Operator::LessEqual)
+            | (_, true, Operator::Greater)
+            | (_, true, Operator::GreaterEqual)
+            | (_, false, Operator::Matches)
+            | (_, false, Operator::NotMatches) => {
+                return Err(anyhow!(
+                    "Operator {} is not supported for field {} at position {}",
+                    operator.as_str(),
+                    name,
+                    operator_position
+                ))
+            }
+            (_, true, operator) => {
+                return Err(anyhow!(
+                    "Operator {} needs a string for field {} at position {}",
+                    operator.as_str(),
+                    name,
+                    operator_position
+                ))
+            }
+            (_, false, operator) => {
+                return Err(anyhow!(
+                    "Operator {} needs a number for field {} at position {}",
+                    operator.as_str(),
+                    name,
+                    operator_position
+                ))
+            }
+        };
+        Ok(Expr::Compare(field, operator, value))
+    }
+}
+
+/// Process filter such as `user == "postgres" && cpu > 5 || name ~ "java.*"`.
+///
+/// Comparisons are joined with `&&`, `||` and `!` and grouped with parentheses.
+/// Text fields (`name`, `user`, `path`) take `==`, `!=`, `~` and `!~` (regex);
+/// numeric fields (`pid`, `ppid`, `cpu`, `mem`, `rss`, `threads`, `priority`,
+/// `nice`, `processor`, `read_rate`, `write_rate`) take `==`, `!=`, `<`, `<=`, `>`, `>=`.
+/// Parentheses and `!` nest up to 64 levels.
+#[derive(Clone)]
+pub struct Filter {
+    expr: Expr,
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            index: 0,
+            input_len: input.len(),
+            depth: 0,
+        };
+        let expr = parser.parse_or()?;
+        if parser.peek().is_some() {
+            return Err(anyhow!(
+                "Unexpected input at position {}",
+                parser.position()
+            ));
+        }
+        Ok(Self { expr })
+    }
+    pub fn matches(&self, proc_info: &Process) -> bool {
+        self.expr.matches(proc_info)
+    }
+}
+
+/// Keeps the nodes that match along with all of their ancestors; the root is always kept.
+pub fn filter_process_tree(proc_tree: &ProcessTree, filter: &Filter) -> ProcessTree {
+    let mut root = ProcessTreeNode {
+        proc_info: proc_tree.root.proc_info.clone(),
+        children: Vec::new(),
+    };
+    root.children = proc_tree
+        .root
+        .children
+        .iter()
+        .filter_map(|child| filter_process_tree_node(child, filter))
+        .collect();
+    ProcessTree { root }
+}
+
+fn filter_process_tree_node(
+    proc_node: &ProcessTreeNode,
+    filter: &Filter,
+) -> Option<ProcessTreeNode> {
+    let children: Vec<ProcessTreeNode> = proc_node
+        .children
+        .iter()
+        .filter_map(|child| filter_process_tree_node(child, filter))
+        .collect();
+    if children.is_empty() && !filter.matches(&proc_node.proc_info) {
+        return None;
+    }
+    Some(ProcessTreeNode {
+        proc_info: proc_node.proc_info.clone(),
+        children,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build_process_tree;
+    use crate::fixture::{Fixture, FixtureProc};
+    use crate::System;
+
+    fn process(pid: u32, name: &str, user: &str, cpu_used: f32) -> Process {
+        let mut proc_info = Process::new();
+        proc_info.pid = pid;
+        proc_info.name = name.to_string();
+        proc_info.user = user.to_string();
+        proc_info.cpu_used = cpu_used;
+        proc_info
+    }
+
+    fn matching_pids(input: &str, procs: &[Process]) -> Vec<u32> {
+        let filter = Filter::parse(input).unwrap();
+        procs
+            .iter()
+            .filter(|proc_info| filter.matches(proc_info))
+            .map(|proc_info| proc_info.pid)
+            .collect()
+    }
+
+    fn parse_error(input: &str) -> String {
+        match Filter::parse(input) {
+            Ok(_) => panic!("{} should not parse", input),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let procs = [
+            process(1, "init", "root", 10f32),
+            process(2, "java", "app", 10f32),
+            process(3, "java", "app", 80f32),
+        ];
+        let input = "pid == 1 || name == java && cpu > 50";
+        assert_eq!(matching_pids(input, &procs), vec![1, 3]);
+        let input = "(pid == 1 || name == java) && cpu > 50";
+        assert_eq!(matching_pids(input, &procs), vec![3]);
+        let input = "pid == 1 || pid == 2 || pid == 3 && user == root";
+        assert_eq!(matching_pids(input, &procs), vec![1, 2]);
+    }
+
+    #[test]
+    fn not_applies_to_the_next_comparison_or_group() {
+        let procs = [
+            process(1, "init", "root", 10f32),
+            process(2, "java", "app", 80f32),
+        ];
+        assert_eq!(matching_pids("!pid == 1 && cpu > 5", &procs), vec![2]);
+        assert_eq!(
+            matching_pids("!(pid == 1 || cpu > 50)", &procs),
+            Vec::<u32>::new()
+        );
+        assert_eq!(matching_pids("!!user == root", &procs), vec![1]);
+    }
+
+    #[test]
+    fn strings_unescape_quotes_and_backslashes_only() {
+        let procs = [
+            process(1, r#"say "hi"\now"#, "root", 0f32),
+            process(2, "worker 42", "root", 0f32),
+        ];
+        assert_eq!(
+            matching_pids(r#"name == "say \"hi\"\\now""#, &procs),
+            vec![1]
+        );
+        // Other escapes reach the regex unchanged
+        assert_eq!(matching_pids(r#"name ~ "\d+$""#, &procs), vec![2]);
+    }
+
+    #[test]
+    fn patterns_match_anywhere_unless_anchored() {
+        let procs = [
+            process(1, "postgres", "postgres", 0f32),
+            process(2, "pg_dump", "postgres", 0f32),
+            process(3, "bash", "root", 0f32),
+        ];
+        assert_eq!(matching_pids("name ~ gres", &procs), vec![1]);
+        assert_eq!(matching_pids(r#"name ~ "^p""#, &procs), vec![1, 2]);
+        assert_eq!(matching_pids(r#"name !~ "^p""#, &procs), vec![3]);
+        assert_eq!(matching_pids("user != postgres", &procs), vec![3]);
+    }
+
+    #[test]
+    fn errors_name_their_position() {
+        for (input, message) in [
+            (r#"name == "abc"#, "Unterminated string at position 8"),
+            ("cpu > 1.2.3", "Invalid number 1.2.3 at position 6"),
+            (
+                "cpu > 5 & mem > 1",
+                "Unexpected character '&' at position 8",
+            ),
+            ("(cpu > 5", "Missing ')' for '(' at position 0"),
+            ("cpu > 5 &&", "Expected a field name at position 10"),
+            ("cpuu > 5", "Unknown field cpuu at position 0"),
+            ("cpu 5", "Expected a comparison operator at position 4"),
+            (
+                "name < 5",
+                "Operator < is not supported for field name at position 5",
+            ),
+            (
+                r#"cpu ~ "x""#,
+                "Operator ~ is not supported for field cpu at position 4",
+            ),
+            (
+                "name == >",
+                "Operator == needs a string for field name at position 5",
+            ),
+            (
+                r#"cpu > "x""#,
+                "Operator > needs a number for field cpu at position 4",
+            ),
+            ("cpu > 5 )", "Unexpected input at position 8"),
+        ] {
+            assert_eq!(parse_error(input), message, "for {}", input);
+        }
+        assert!(parse_error(r#"name ~ "(""#).starts_with("Invalid pattern at position 7: "));
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let nested = |depth: usize| format!("{}pid > 1{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(Filter::parse(&nested(MAX_NESTING)).is_ok());
+        assert_eq!(
+            parse_error(&nested(MAX_NESTING + 1)),
+            "Nesting deeper than 64 levels at position 64"
+        );
+        assert_eq!(
+            parse_error(&format!("{}pid > 1", "!".repeat(30000))),
+            "Nesting deeper than 64 levels at position 64"
+        );
+        assert!(parse_error(&nested(30000)).starts_with("Nesting deeper than 64 levels"));
+        // Long chains stay flat
+        let chain = vec!["pid > 1"; 30000].join(" && ");
+        assert!(Filter::parse(&chain)
+            .unwrap()
+            .matches(&process(2, "a", "b", 0f32)));
+    }
+
+    #[test]
+    fn filtered_trees_keep_the_ancestors_of_matches() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        for (pid, ppid) in [(1, 0), (2, 1), (3, 2), (4, 1), (5, 4)] {
+            fixture.write_proc(&FixtureProc::new(pid, ppid)).unwrap();
+        }
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        let proc_tree = build_process_tree(&system);
+        let filtered = filter_process_tree(&proc_tree, &Filter::parse("pid == 3").unwrap());
+        let root = &filtered.root;
+        assert_eq!(root.proc_info.pid, 0);
+        assert_eq!(root.children.len(), 1);
+        let init = &root.children[0];
+        assert_eq!(init.proc_info.pid, 1);
+        assert_eq!(init.children.len(), 1);
+        assert_eq!(init.children[0].proc_info.pid, 2);
+        assert_eq!(init.children[0].children[0].proc_info.pid, 3);
+        let filtered = filter_process_tree(&proc_tree, &Filter::parse("pid > 100").unwrap());
+        assert_eq!(filtered.root.proc_info.pid, 0);
+        assert!(filtered.root.children.is_empty());
+    }
+}
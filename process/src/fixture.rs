@@ -0,0 +1,219 @@
+//! Procfs trees written below a temporary root, shared by the unit tests, the refresh
+//! benchmark and, through the `fixture` feature, the tests of dependent crates.
+#![allow(dead_code)]
+
+use std::fs;
+use std::io;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const USERS: u32 = 50;
+pub const FIRST_UID: u32 = 1000;
+// Thread ids of a process are its pid plus multiples of this, so they never clash with pids
+const TID_STRIDE: u32 = 1_000_000;
+
+static NEXT_FIXTURE: AtomicUsize = AtomicUsize::new(0);
+
+/// A temporary root directory, removed again when dropped.
+pub struct Fixture {
+    root: PathBuf,
+}
+
+impl Fixture {
+    pub fn new() -> io::Result<Self> {
+        let root = std::env::temp_dir().join(format!(
+            "forge_view_fixture_{}_{}",
+            std::process::id(),
+            NEXT_FIXTURE.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(root.join("proc"))?;
+        Ok(Self { root })
+    }
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+    /// Writes `contents` to `path` below the root, creating missing directories.
+    pub fn write(&self, path: &str, contents: &str) -> io::Result<()> {
+        let path = self.root.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    }
+    /// Writes `/proc/stat` with 4 cores, `meminfo`, `uptime` and a passwd with `USERS` users.
+    pub fn write_system(&self) -> io::Result<()> {
+        self.write(
+            "proc/stat",
+            "cpu  4000 0 2000 90000 100 0 50 0 0 0\n\
+             cpu0 1000 0 500 22500 25 0 12 0 0 0\n\
+             cpu1 1000 0 500 22500 25 0 13 0 0 0\n\
+             cpu2 1000 0 500 22500 25 0 12 0 0 0\n\
+             cpu3 1000 0 500 22500 25 0 13 0 0 0\n\
+             intr 12345 0 0\nctxt 67890\n",
+        )?;
+        self.write(
+            "proc/meminfo",
+            "MemTotal:       16384000 kB\nMemFree:         8192000 kB\n\
+             MemAvailable:   12288000 kB\nBuffers:          512000 kB\n\
+             Cached:          2048000 kB\nSwapTotal:       1024000 kB\n\
+             SwapFree:         512000 kB\n",
+        )?;
+        self.write("proc/uptime", "100000.00 390000.00\n")?;
+        let passwd: String = (0..USERS)
+            .map(|user| {
+                format!(
+                    "user{0}:x:{1}:{1}::/home/user{0}:/bin/sh\n",
+                    user,
+                    FIRST_UID + user
+                )
+            })
+            .collect();
+        self.write("etc/passwd", &passwd)
+    }
+    /// Writes the `stat` of the process and of each of its threads, e.g. to advance its CPU times.
+    pub fn write_stat(&self, proc: &FixtureProc) -> io::Result<()> {
+        let pid_dir = format!("proc/{}", proc.pid);
+        self.write(&format!("{}/stat", pid_dir), &proc.stat_line(proc.pid))?;
+        for tid in proc.get_tids() {
+            self.write(
+                &format!("{}/task/{}/stat", pid_dir, tid),
+                &proc.stat_line(tid),
+            )?;
+        }
+        Ok(())
+    }
+    pub fn write_proc(&self, proc: &FixtureProc) -> io::Result<()> {
+        let pid_dir = format!("proc/{}", proc.pid);
+        self.write_stat(proc)?;
+        self.write(
+            &format!("{}/status", pid_dir),
+            &format!(
+                "Name:\t{name}\nUmask:\t0022\nState:\tS (sleeping)\nTgid:\t{pid}\nPid:\t{pid}\n\
+                 PPid:\t{ppid}\nUid:\t{uid}\t{uid}\t{uid}\t{uid}\nGid:\t{uid}\t{uid}\t{uid}\t{uid}\n\
+                 VmRSS:\t    4096 kB\nRssAnon:\t    1024 kB\nRssFile:\t    2048 kB\n\
+                 RssShmem:\t    1024 kB\nVmSwap:\t       8 kB\nThreads:\t{threads}\n\
+                 Cpus_allowed:\tf\nCpus_allowed_list:\t0-1,3\n",
+                name = proc.name,
+                pid = proc.pid,
+                ppid = proc.ppid,
+                uid = proc.uid,
+                threads = proc.threads
+            ),
+        )?;
+        self.write(
+            &format!("{}/smaps_rollup", pid_dir),
+            "55d0c0a00000-7ffd1c1f2000 ---p 00000000 00:00 0                          [rollup]\n\
+             Rss:                4096 kB\nPss:                2560 kB\n\
+             Shared_Clean:       1536 kB\nShared_Dirty:          0 kB\n\
+             Private_Clean:       512 kB\nPrivate_Dirty:       2048 kB\n\
+             Referenced:         4096 kB\nAnonymous:          2048 kB\nSwap:                  8 kB\n",
+        )?;
+        self.write(
+            &format!("{}/io", pid_dir),
+            "rchar: 0\nwchar: 0\nsyscr: 0\nsyscw: 0\nread_bytes: 0\nwrite_bytes: 0\n\
+             cancelled_write_bytes: 0\n",
+        )?;
+        let fd_dir = self.root.join(&pid_dir).join("fd");
+        fs::create_dir_all(&fd_dir)?;
+        // Targets with their octal open(2) flags and file position
+        let mut fd_targets = vec![
+            (String::from("/dev/null"), "0100000", 0),
+            (format!("/var/log/worker-{}.log", proc.pid), "0102001", 4096),
+            (format!("pipe:[{}]", 200_000 + proc.pid), "00", 0),
+        ];
+        if let Some(socket_inode) = proc.socket_inode {
+            fd_targets.push((format!("socket:[{}]", socket_inode), "02", 0));
+        }
+        for (fd, (target, flags, position)) in fd_targets.iter().enumerate() {
+            symlink(target, fd_dir.join(fd.to_string()))?;
+            self.write(
+                &format!("{}/fdinfo/{}", pid_dir, fd),
+                &format!("pos:\t{}\nflags:\t{}\nmnt_id:\t25\n", position, flags),
+            )?;
+        }
+        symlink("/usr/bin/worker", self.root.join(&pid_dir).join("exe"))
+    }
+    /// Writes the system files and `procs` processes below pid 1, each listening on a TCP
+    /// port and running up to 4 threads.
+    pub fn write_procs(&self, procs: u32) -> io::Result<()> {
+        self.write_system()?;
+        let mut tcp = String::from(
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  \
+             timeout inode\n",
+        );
+        for pid in 1..=procs {
+            let mut proc = FixtureProc::new(pid, if pid == 1 { 0 } else { 1 });
+            proc.uid = FIRST_UID + pid % USERS;
+            proc.busy_time = (pid as u64 % 700, pid as u64 % 300);
+            proc.threads = 1 + pid % 4;
+            self.write_proc(&proc)?;
+            if let Some(socket_inode) = proc.socket_inode {
+                tcp.push_str(&format!(
+                    "{:4}: 0100007F:{:04X} 00000000:0000 0A 00000000:00000000 00:00000000 \
+                     00000000 {:5}        0 {} 1 0000000000000000 100 0 0 10 0\n",
+                    pid - 1,
+                    pid % 65536,
+                    proc.uid,
+                    socket_inode
+                ));
+            }
+        }
+        self.write("proc/net/tcp", &tcp)
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+pub struct FixtureProc {
+    pub pid: u32,
+    pub ppid: u32,
+    pub name: String,
+    pub uid: u32,
+    /// utime and stime in clock ticks
+    pub busy_time: (u64, u64),
+    pub starttime: u64,
+    pub nice: i64,
+    pub processor: u32,
+    pub threads: u32,
+    pub socket_inode: Option<u64>,
+}
+
+impl FixtureProc {
+    pub fn new(pid: u32, ppid: u32) -> Self {
+        Self {
+            pid,
+            ppid,
+            name: format!("worker {}", pid),
+            uid: FIRST_UID,
+            busy_time: (0, 0),
+            starttime: 1000 + pid as u64 * 10,
+            nice: 0,
+            processor: pid % 4,
+            threads: 1,
+            socket_inode: Some(100_000 + pid as u64),
+        }
+    }
+    pub fn get_tids(&self) -> Vec<u32> {
+        (0..self.threads)
+            .map(|thread| self.pid + thread * TID_STRIDE)
+            .collect()
+    }
+    fn stat_line(&self, id: u32) -> String {
+        // Index 0 is field 3 (state) of proc(5)
+        let mut fields: Vec<String> = vec![String::from("0"); 50];
+        fields[0] = String::from("S");
+        fields[1] = self.ppid.to_string();
+        fields[11] = self.busy_time.0.to_string();
+        fields[12] = self.busy_time.1.to_string();
+        fields[15] = (20 + self.nice).to_string();
+        fields[16] = self.nice.to_string();
+        fields[19] = self.starttime.to_string();
+        fields[36] = self.processor.to_string();
+        format!("{} ({}) {}\n", id, self.name, fields.join(" "))
+    }
+}
@@ -0,0 +1,257 @@
+use crate::System;
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+
+pub const DEFAULT_HISTORY_DURATION: Duration = Duration::from_secs(300);
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SystemSample {
+    timestamp: SystemTime,
+    cpu_used: f32,
+    mem_used: f32,
+}
+
+impl SystemSample {
+    pub(crate) fn new(timestamp: SystemTime, cpu_used: f32, mem_used: f32) -> Self {
+        Self {
+            timestamp,
+            cpu_used,
+            mem_used,
+        }
+    }
+    pub fn get_timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+    pub fn get_cpu_used(&self) -> f32 {
+        self.cpu_used
+    }
+    pub fn get_mem_used(&self) -> f32 {
+        self.mem_used
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessSample {
+    timestamp: SystemTime,
+    cpu_used: f32,
+    mem_used: f32,
+    read_rate: f32,
+    write_rate: f32,
+}
+
+impl ProcessSample {
+    pub(crate) fn new(
+        timestamp: SystemTime,
+        cpu_used: f32,
+        mem_used: f32,
+        read_rate: f32,
+        write_rate: f32,
+    ) -> Self {
+        Self {
+            timestamp,
+            cpu_used,
+            mem_used,
+            read_rate,
+            write_rate,
+        }
+    }
+    pub fn get_timestamp(&self) -> SystemTime {
+        self.timestamp
+    }
+    pub fn get_cpu_used(&self) -> f32 {
+        self.cpu_used
+    }
+    pub fn get_mem_used(&self) -> f32 {
+        self.mem_used
+    }
+    pub fn get_read_rate(&self) -> f32 {
+        self.read_rate
+    }
+    pub fn get_write_rate(&self) -> f32 {
+        self.write_rate
+    }
+}
+
+// Samples of one process, dropped when the PID is reused by a process with another start time
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct ProcessHistory {
+    starttime: u64,
+    samples: VecDeque<ProcessSample>,
+}
+
+/// Samples recorded on every refresh, keeping only those newer than the configured duration.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct History {
+    duration: Duration,
+    system_samples: VecDeque<SystemSample>,
+    proc_samples: HashMap<u32, ProcessHistory>,
+}
+
+impl History {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            system_samples: VecDeque::new(),
+            proc_samples: HashMap::new(),
+        }
+    }
+    pub fn get_duration(&self) -> Duration {
+        self.duration
+    }
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+        if let Some(latest) = self.system_samples.back() {
+            let latest = latest.timestamp;
+            self.prune(latest);
+        }
+    }
+    pub fn get_system_samples(&self) -> &VecDeque<SystemSample> {
+        &self.system_samples
+    }
+    pub fn get_proc_samples(&self, pid: u32) -> Option<&VecDeque<ProcessSample>> {
+        self.proc_samples
+            .get(&pid)
+            .map(|proc_history| &proc_history.samples)
+    }
+    pub fn get_pids(&self) -> Vec<u32> {
+        self.proc_samples.keys().copied().collect()
+    }
+    pub fn clear(&mut self) {
+        self.system_samples.clear();
+        self.proc_samples.clear();
+    }
+    // System samples and the samples of `pid` only
+    #[cfg(feature = "agent")]
+    pub(crate) fn with_pid(&self, pid: u32) -> History {
+        let mut history = History::new(self.duration);
+        history.system_samples = self.system_samples.clone();
+        if let Some(proc_history) = self.proc_samples.get(&pid) {
+            history.proc_samples.insert(pid, proc_history.clone());
+        }
+        history
+    }
+    /// Adds the samples of one refresh of `system`.
+    pub(crate) fn record(&mut self, system: &System, timestamp: SystemTime) {
+        for (pid, process_info) in system.procs.iter() {
+            self.push_proc_sample(
+                *pid,
+                process_info.starttime,
+                ProcessSample::new(
+                    timestamp,
+                    process_info.cpu_used,
+                    process_info.mem_used,
+                    process_info.io.get_read_rate(),
+                    process_info.io.get_write_rate(),
+                ),
+            );
+        }
+        // Pushing the system sample last also prunes the process samples
+        self.push_system_sample(SystemSample::new(
+            timestamp,
+            system.cpu_used,
+            system.mem_info.get_used_percent(),
+        ));
+    }
+    /// Adds the samples of `latest` newer than those already here, e.g. the ones of a
+    /// source that keeps only its latest refresh.
+    pub(crate) fn append(&mut self, latest: History) {
+        let newest = self
+            .system_samples
+            .back()
+            .map(|system_sample| system_sample.timestamp);
+        let is_new = |timestamp: SystemTime| newest.is_none_or(|newest| timestamp > newest);
+        for (pid, proc_history) in latest.proc_samples {
+            for proc_sample in proc_history.samples {
+                if is_new(proc_sample.timestamp) {
+                    self.push_proc_sample(pid, proc_history.starttime, proc_sample);
+                }
+            }
+        }
+        // Pushing the system samples last also prunes the process samples
+        for system_sample in latest.system_samples {
+            if is_new(system_sample.timestamp) {
+                self.push_system_sample(system_sample);
+            }
+        }
+    }
+    pub(crate) fn push_system_sample(&mut self, system_sample: SystemSample) {
+        let timestamp = system_sample.timestamp;
+        self.system_samples.push_back(system_sample);
+        self.prune(timestamp);
+    }
+    pub(crate) fn push_proc_sample(
+        &mut self,
+        pid: u32,
+        starttime: u64,
+        proc_sample: ProcessSample,
+    ) {
+        let proc_history = self.proc_samples.entry(pid).or_insert(ProcessHistory {
+            starttime,
+            samples: VecDeque::new(),
+        });
+        if proc_history.starttime != starttime {
+            proc_history.starttime = starttime;
+            proc_history.samples.clear();
+        }
+        proc_history.samples.push_back(proc_sample);
+    }
+    fn prune(&mut self, latest: SystemTime) {
+        let is_expired = |timestamp: SystemTime| {
+            latest
+                .duration_since(timestamp)
+                .map(|age| age > self.duration)
+                .unwrap_or(false)
+        };
+        while let Some(system_sample) = self.system_samples.front() {
+            if !is_expired(system_sample.timestamp) {
+                break;
+            }
+            self.system_samples.pop_front();
+        }
+        self.proc_samples.retain(|_, proc_history| {
+            while let Some(proc_sample) = proc_history.samples.front() {
+                if !is_expired(proc_sample.timestamp) {
+                    break;
+                }
+                proc_history.samples.pop_front();
+            }
+            !proc_history.samples.is_empty()
+        });
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_DURATION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appending_skips_samples_already_kept() {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1000);
+        let mut latest = History::new(Duration::ZERO);
+        let mut history = History::default();
+        // The second refresh publishes the first one again
+        for secs in [0, 0, 1] {
+            let timestamp = start + Duration::from_secs(secs);
+            latest.push_proc_sample(
+                7,
+                100,
+                ProcessSample::new(timestamp, 1f32, 2f32, 0f32, 0f32),
+            );
+            latest.push_system_sample(SystemSample::new(timestamp, 3f32, 4f32));
+            history.append(latest.clone());
+        }
+        assert_eq!(latest.get_system_samples().len(), 1);
+        assert_eq!(history.get_system_samples().len(), 2);
+        assert_eq!(history.get_proc_samples(7).map(VecDeque::len), Some(2));
+    }
+}
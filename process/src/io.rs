@@ -0,0 +1,153 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Zero for processes whose `/proc/<pid>/io` is not readable by the current user.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessIo {
+    read_bytes: u64,
+    write_bytes: u64,
+    read_syscalls: u64,
+    write_syscalls: u64,
+    cancelled_write_bytes: u64,
+    read_rate: f32,
+    write_rate: f32,
+}
+
+impl ProcessIo {
+    pub fn get_read_bytes(&self) -> u64 {
+        self.read_bytes
+    }
+    pub fn get_write_bytes(&self) -> u64 {
+        self.write_bytes
+    }
+    pub fn get_read_syscalls(&self) -> u64 {
+        self.read_syscalls
+    }
+    pub fn get_write_syscalls(&self) -> u64 {
+        self.write_syscalls
+    }
+    pub fn get_cancelled_write_bytes(&self) -> u64 {
+        self.cancelled_write_bytes
+    }
+    /// Bytes per second read from storage since the previous refresh.
+    pub fn get_read_rate(&self) -> f32 {
+        self.read_rate
+    }
+    /// Bytes per second written to storage since the previous refresh.
+    pub fn get_write_rate(&self) -> f32 {
+        self.write_rate
+    }
+    pub(crate) fn set_rates(&mut self, prev_io: &ProcessIo, elapsed_secs: f32) {
+        if elapsed_secs <= 0f32 {
+            return;
+        }
+        self.read_rate = self.read_bytes.saturating_sub(prev_io.read_bytes) as f32 / elapsed_secs;
+        self.write_rate =
+            self.write_bytes.saturating_sub(prev_io.write_bytes) as f32 / elapsed_secs;
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn get_proc_io(root: &Path, pid: u32) -> Result<ProcessIo> {
+    let buffer = fs::read_to_string(root.join("proc").join(pid.to_string()).join("io"))?;
+    let mut fields: HashMap<&str, u64> = HashMap::new();
+    for line in buffer.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            fields.insert(key, value.trim().parse::<u64>()?);
+        }
+    }
+    let field = |key: &str| fields.get(key).copied().unwrap_or(0);
+    Ok(ProcessIo {
+        read_bytes: field("read_bytes"),
+        write_bytes: field("write_bytes"),
+        read_syscalls: field("syscr"),
+        write_syscalls: field("syscw"),
+        cancelled_write_bytes: field("cancelled_write_bytes"),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, FixtureProc};
+    use crate::System;
+    use std::thread::sleep;
+    use std::time::{Duration, Instant};
+
+    fn write_io(fixture: &Fixture, pid: u32, read_bytes: u64, write_bytes: u64) {
+        fixture
+            .write(
+                &format!("proc/{}/io", pid),
+                &format!(
+                    "rchar: 0\nwchar: 0\nsyscr: 7\nsyscw: 3\nread_bytes: {}\n\
+                     write_bytes: {}\ncancelled_write_bytes: 0\n",
+                    read_bytes, write_bytes
+                ),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn rates_are_bytes_per_second_since_the_previous_sample() {
+        let prev_io = ProcessIo {
+            read_bytes: 1000,
+            write_bytes: 5000,
+            ..Default::default()
+        };
+        let mut io = ProcessIo {
+            read_bytes: 9000,
+            write_bytes: 4000,
+            ..Default::default()
+        };
+        io.set_rates(&prev_io, 2f32);
+        assert_eq!(io.get_read_rate(), 4000f32);
+        // Counters going backwards are not a negative rate
+        assert_eq!(io.get_write_rate(), 0f32);
+        io.set_rates(&prev_io, 0f32);
+        assert_eq!(io.get_read_rate(), 4000f32);
+    }
+
+    #[test]
+    fn rates_are_measured_between_two_refreshes() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        fixture.write_proc(&FixtureProc::new(42, 1)).unwrap();
+        write_io(&fixture, 42, 4096, 1024);
+        let mut system = System::with_root(fixture.get_root());
+        let first_start = Instant::now();
+        system.refresh_system_info().unwrap();
+        let first_end = Instant::now();
+        // Nothing to compare the first sample with
+        let io = system.get_proc_info(&42).unwrap().get_io();
+        assert_eq!(io.get_read_bytes(), 4096);
+        assert_eq!(io.get_read_syscalls(), 7);
+        assert_eq!(io.get_read_rate(), 0f32);
+        assert_eq!(io.get_write_rate(), 0f32);
+
+        sleep(Duration::from_millis(50));
+        write_io(&fixture, 42, 4096 + 100_000, 1024);
+        fixture.write_proc(&FixtureProc::new(43, 1)).unwrap();
+        write_io(&fixture, 43, 100_000, 100_000);
+        let second_start = Instant::now();
+        system.refresh_system_info().unwrap();
+        let second_end = Instant::now();
+        let (min_secs, max_secs) = (
+            (second_start - first_end).as_secs_f32(),
+            (second_end - first_start).as_secs_f32(),
+        );
+        let read_rate = system.get_proc_info(&42).unwrap().get_io().get_read_rate();
+        assert!(read_rate >= 100_000f32 / max_secs && read_rate <= 100_000f32 / min_secs);
+        assert_eq!(
+            system.get_proc_info(&42).unwrap().get_io().get_write_rate(),
+            0f32
+        );
+        // A process first seen in this refresh has no previous counters either
+        let io = system.get_proc_info(&43).unwrap().get_io();
+        assert_eq!(io.get_read_rate(), 0f32);
+        assert_eq!(io.get_write_rate(), 0f32);
+    }
+}
@@ -1,13 +1,71 @@
+#[cfg(feature = "agent")]
+mod agent;
+pub mod control;
+mod cpu;
+#[cfg(feature = "serde")]
+mod export;
+mod fd;
+mod filter;
+#[cfg(any(test, feature = "fixture"))]
+#[doc(hidden)]
+pub mod fixture;
+mod history;
+mod io;
+mod mem;
+mod net;
+mod openmetrics;
+#[cfg(feature = "record")]
+mod record;
+mod sampler;
+mod sched;
+mod thread;
+mod users;
+
+#[cfg(feature = "agent")]
+pub use agent::{
+    default_agent_socket, run_agent, AgentClient, Request, Response, TcpListen,
+    AGENT_PROTOCOL_VERSION,
+};
 use anyhow::Result;
+use cpu::{get_cpu_times, get_cpu_topology, CpuTimes};
+pub use cpu::{parse_cpu_list, CpuCoreUsage, CpuTopology};
+#[cfg(feature = "serde")]
+pub use export::{export_csv, export_json, export_ndjson, ExportFormat};
+use fd::get_open_files;
+pub use fd::{FileDescriptorKind, OpenFile};
+pub use filter::{filter_process_tree, Filter};
+pub use history::{History, ProcessSample, SystemSample, DEFAULT_HISTORY_DURATION};
+use io::get_proc_io;
+pub use io::ProcessIo;
+use mem::{get_mem_info, get_proc_memory, parse_kb_value};
+pub use mem::{MemoryInfo, ProcessMemory};
+use net::{get_all_sockets, get_proc_socket_inodes};
+pub use net::{Socket, SocketProtocol, SocketState};
 use nix::unistd::{sysconf, SysconfVar};
+pub use openmetrics::{encode_openmetrics, OPENMETRICS_CONTENT_TYPE};
+#[cfg(feature = "record")]
+pub use record::{Recorder, Recording, RECORDING_VERSION};
+pub use sampler::{Sampler, SnapshotSource};
+pub use sched::{IoPriority, IoPriorityClass, ProcessScheduling, SchedulingPolicy};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::collections::HashMap;
 use std::fs;
+use std::mem::{replace, take};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use thread::get_thread_samples;
+pub use thread::Thread;
+use users::UserNames;
+
+#[cfg(target_os = "linux")]
+fn proc_pid_path(root: &Path, pid: u32, file: &str) -> PathBuf {
+    root.join("proc").join(pid.to_string()).join(file)
+}
 
 #[cfg(target_os = "linux")]
-fn get_all_pids() -> Result<Vec<String>> {
+fn get_all_pids(root: &Path) -> Result<Vec<String>> {
     let mut pids_list: Vec<String> = Vec::new();
-    let proc_file_path = fs::read_dir("/proc")?;
+    let proc_file_path = fs::read_dir(root.join("proc"))?;
     for file in proc_file_path {
         if let Some(file_name) = file?.file_name().to_str() {
             if let Some(pid) = file_name.chars().next() {
@@ -21,198 +79,155 @@ fn get_all_pids() -> Result<Vec<String>> {
 }
 
 #[cfg(target_os = "linux")]
-fn get_proc_name(pid: u32) -> Result<String> {
-    let mut proc_name = String::new();
-    let buffer = fs::read_to_string(format!("/proc/{}/status", pid))?;
+struct ProcStatus {
+    name: String,
+    uid: u32,
+    ppid: u32,
+    cpus_allowed: Vec<usize>,
+    memory: ProcessMemory,
+}
+/// Reads `/proc/<pid>/status` in a single pass.
+#[cfg(target_os = "linux")]
+fn get_proc_status(root: &Path, pid: u32) -> Result<ProcStatus> {
+    let buffer = fs::read_to_string(proc_pid_path(root, pid, "status"))?;
+    let mut proc_status = ProcStatus {
+        name: String::new(),
+        uid: 0,
+        ppid: 0,
+        cpus_allowed: Vec::new(),
+        memory: ProcessMemory::default(),
+    };
     for line in buffer.lines() {
-        if line.contains("Name:") {
-            proc_name = line
-                .split_whitespace()
-                .last()
-                .unwrap_or_default()
-                .to_string();
-            break;
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Name" => proc_status.name = value.to_string(),
+            // Real, effective, saved and filesystem uids, the last one is shown
+            "Uid" => {
+                proc_status.uid = value
+                    .split_whitespace()
+                    .last()
+                    .unwrap_or_default()
+                    .parse::<u32>()?
+            }
+            "PPid" => proc_status.ppid = value.parse::<u32>()?,
+            "Cpus_allowed_list" => proc_status.cpus_allowed = parse_cpu_list(value)?,
+            _ => {
+                if let Some(value) = parse_kb_value(value) {
+                    proc_status.memory.set_status_field(key, value);
+                }
+            }
         }
     }
-    Ok(proc_name)
+    Ok(proc_status)
 }
 #[cfg(target_os = "linux")]
-fn get_proc_cpu_usage(pid: u32) -> Result<f32> {
-    let system_clock_tick = sysconf(SysconfVar::CLK_TCK)?.unwrap_or(100) as f32;
-    let buffer = fs::read_to_string(format!("/proc/{}/stat", pid))?;
-    let proc_utime = buffer
-        .split_whitespace()
-        .nth(13)
-        .unwrap_or("0")
-        .parse::<f32>()?;
-    let proc_stime = buffer
-        .split_whitespace()
-        .nth(14)
-        .unwrap_or("0")
-        .parse::<f32>()?;
-    let proc_starttime = buffer
-        .split_whitespace()
-        .nth(21)
-        .unwrap_or("0")
-        .parse::<f32>()?;
-    let system_uptime = fs::read_to_string("/proc/uptime")?
-        .split_whitespace()
-        .next()
-        .unwrap()
-        .parse::<f32>()?;
-    let total_time = proc_utime + proc_stime;
-    let seconds = system_uptime - (proc_starttime / system_clock_tick);
-    let num_of_cpus = fs::read_to_string("/proc/cpuinfo")?
-        .lines()
-        .filter(|line| line.contains("processor"))
-        .count() as f32;
-    Ok(100f32 * ((total_time / system_clock_tick) / seconds) / num_of_cpus)
+fn get_proc_stat_fields(buffer: &str) -> Vec<&str> {
+    // The comm field may contain spaces, so start counting after its closing ')'
+    match buffer.rfind(')') {
+        Some(index) => buffer[index + 1..].split_whitespace().collect(),
+        None => Vec::new(),
+    }
 }
 #[cfg(target_os = "linux")]
-fn get_proc_mem_usage(pid: u32) -> Result<f32> {
-    let mut rss = 0f32; // in MB
-    let mut buffer = fs::read_to_string(format!("/proc/{}/status", pid))?;
-    for line in buffer.lines() {
-        if line.contains("VmRSS:") {
-            rss = line
-                .split_whitespace()
-                .nth(1)
-                .unwrap_or("0")
-                .parse::<f32>()?;
-            break;
-        }
-    }
-    buffer = fs::read_to_string("/proc/meminfo")?;
-    let mut total_mem = 0f32;
-    for line in buffer.lines() {
-        if line.contains("MemTotal:") {
-            total_mem = line
-                .split_whitespace()
-                .nth(1)
-                .unwrap_or("0")
-                .parse::<f32>()?;
-            break;
-        }
-    }
-    if total_mem != 0f32 {
-        return Ok(rss / total_mem * 100f32);
-    }
-    Ok(0f32)
+struct ProcStat {
+    busy_time: u64,
+    starttime: u64,
+    processor: u32,
+    scheduling: ProcessScheduling,
 }
 #[cfg(target_os = "linux")]
-fn get_proc_path(pid: u32) -> Result<String> {
-    let buffer = fs::read_link(format!("/proc/{}/exe", pid));
-    if let Ok(proc_path) = buffer {
-        return Ok(proc_path.to_str().unwrap_or("").to_string());
-    }
-    Ok("".to_string())
+fn get_proc_stat(root: &Path, pid: u32) -> Result<ProcStat> {
+    let buffer = fs::read_to_string(proc_pid_path(root, pid, "stat"))?;
+    let stat_fields = get_proc_stat_fields(&buffer);
+    // Index 0 is field 3 (state) of proc(5)
+    let field = |index: usize| stat_fields.get(index).copied().unwrap_or("0");
+    Ok(ProcStat {
+        busy_time: field(11).parse::<u64>()? + field(12).parse::<u64>()?,
+        starttime: field(19).parse::<u64>()?,
+        processor: field(36).parse::<u32>()?,
+        scheduling: ProcessScheduling::new(
+            field(15).parse::<i64>()?,
+            field(16).parse::<i64>()?,
+            field(38).parse::<i32>()?,
+            field(37).parse::<u32>()?,
+        ),
+    })
 }
+// Policy and kernel priority (`prio`) of `proc/<pid>/sched`, which only exists in kernels
+// built with CONFIG_SCHED_DEBUG
 #[cfg(target_os = "linux")]
-fn get_proc_user(pid: u32) -> Result<String> {
-    let mut proc_user_uid = String::new();
-    let mut buffer = fs::read_to_string(format!("/proc/{}/status", pid))?;
+fn get_proc_sched(root: &Path, pid: u32) -> Option<(i32, i64)> {
+    let buffer = fs::read_to_string(proc_pid_path(root, pid, "sched")).ok()?;
+    let (mut policy, mut prio) = (None, None);
     for line in buffer.lines() {
-        if line.contains("Uid:") {
-            proc_user_uid = line
-                .split_whitespace()
-                .last()
-                .unwrap_or_default()
-                .to_string();
-            break;
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key.trim_end() {
+            "policy" => policy = value.trim().parse::<i32>().ok(),
+            "prio" => prio = value.trim().parse::<i64>().ok(),
+            _ => {}
         }
     }
-    buffer = fs::read_to_string("/etc/passwd")?;
-    for line in buffer.lines() {
-        if let Some(proc_user_line_uid) = line.split(':').nth(2) {
-            if proc_user_line_uid == proc_user_uid {
-                return Ok(line.split(':').next().unwrap().to_string());
-            }
-        }
-    }
-    Ok("".to_string())
+    Some((policy?, prio?))
 }
 #[cfg(target_os = "linux")]
-fn get_proc_ppid(pid: u32) -> Result<u32> {
-    let mut proc_ppid = 0u32;
-    let buffer = fs::read_to_string(format!("/proc/{}/status", pid))?;
-    for line in buffer.lines() {
-        if line.contains("PPid:") {
-            proc_ppid = line
-                .split_whitespace()
-                .last()
-                .unwrap_or_default()
-                .parse::<u32>()?;
-            break;
-        }
-    }
-    Ok(proc_ppid)
+fn get_system_uptime(root: &Path) -> Result<f32> {
+    Ok(fs::read_to_string(root.join("proc/uptime"))?
+        .split_whitespace()
+        .next()
+        .unwrap_or("0")
+        .parse::<f32>()?)
 }
 #[cfg(target_os = "linux")]
-fn get_total_cpu_usage() -> Result<f32> {
-    let mut total_cpu_usage = 0f32;
-    let buffer = fs::read_to_string("/proc/stat")?;
-    if let Some(cpu_metrics_line) = buffer.lines().next() {
-        let mut idle_time = 0f32;
-        let mut index = 0usize;
-        let mut cpu_metrics_splitter = cpu_metrics_line.split_whitespace();
-        while index < 10 {
-            let cpu_metric = cpu_metrics_splitter.next().unwrap_or("0");
-            if index == 0 {
-                index += 1;
-                continue;
-            }
-            if index == 4 {
-                idle_time = cpu_metric.parse::<f32>()?;
-            }
-            total_cpu_usage += cpu_metric.parse::<f32>()?;
-            index += 1;
-        }
-        total_cpu_usage = 100f32 - (idle_time * 100f32) / total_cpu_usage;
+fn get_proc_lifetime_cpu_usage(busy_time: u64, starttime: u64, sample: &RefreshSample) -> f32 {
+    let system_clock_tick = sample.clock_tick;
+    let seconds = sample.system_uptime - (starttime as f32 / system_clock_tick);
+    if seconds <= 0f32 || sample.num_of_cpus == 0 {
+        return 0f32;
     }
-    Ok(total_cpu_usage)
+    100f32 * ((busy_time as f32 / system_clock_tick) / seconds) / sample.num_of_cpus as f32
 }
 #[cfg(target_os = "linux")]
-fn get_total_mem_usage() -> Result<f32> {
-    let buffer = fs::read_to_string("/proc/meminfo")?;
-    let mut free_mem = 0f32;
-    let mut total_mem = 0f32;
-    for line in buffer.lines() {
-        if line.contains("MemFree:") || line.contains("Buffers") || line.contains("Cached") {
-            free_mem += line
-                .split_whitespace()
-                .nth(1)
-                .unwrap_or("0")
-                .parse::<f32>()?;
-        }
-        if line.contains("MemTotal:") {
-            total_mem = line
-                .split_whitespace()
-                .nth(1)
-                .unwrap_or("0")
-                .parse::<f32>()?;
-        }
+fn get_proc_path(root: &Path, pid: u32) -> String {
+    let buffer = fs::read_link(proc_pid_path(root, pid, "exe"));
+    if let Ok(proc_path) = buffer {
+        return proc_path.to_str().unwrap_or("").to_string();
     }
-    Ok(100f32 - (free_mem * 100f32) / total_mem)
+    "".to_string()
 }
-
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Process {
     pid: u32,
     name: String,
     cpu_used: f32,
     mem_used: f32,
+    memory: ProcessMemory,
+    io: ProcessIo,
+    scheduling: ProcessScheduling,
+    cpus_allowed: Vec<usize>,
+    processor: u32,
+    starttime: u64,
     path: String,
     user: String,
     ppid: u32,
+    threads: Vec<Thread>,
+    sockets: Vec<Socket>,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProcessTreeNode {
     pub proc_info: Process,
     pub children: Vec<ProcessTreeNode>,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProcessTree {
     pub root: ProcessTreeNode,
 }
@@ -233,9 +248,17 @@ pub fn build_process_tree(system: &System) -> ProcessTree {
             name: "System Hierarchy".to_string(),
             cpu_used: 0f32,
             mem_used: 0f32,
+            memory: ProcessMemory::default(),
+            io: ProcessIo::default(),
+            scheduling: ProcessScheduling::default(),
+            cpus_allowed: Vec::new(),
+            processor: 0,
+            starttime: 0,
             path: "".to_string(),
             user: "".to_string(),
             ppid: 0,
+            threads: Vec::new(),
+            sockets: Vec::new(),
         }),
     };
     let root = &mut proc_tree.root;
@@ -245,18 +268,20 @@ pub fn build_process_tree(system: &System) -> ProcessTree {
 
 fn build_process_tree_data(proc_node: &mut ProcessTreeNode, system: &System) {
     let mut ppid_map: HashMap<u32, Vec<u32>> = HashMap::new();
-    let pids_str_list = get_all_pids().unwrap_or_default();
-    for pid_str in pids_str_list {
-        if let Ok(pid_u32) = pid_str.parse::<u32>() {
-            if let Some(value) = system.procs.get(&pid_u32) {
-                match ppid_map.entry(value.get_ppid()) {
-                    Vacant(entry) => {
-                        entry.insert(vec![pid_u32]);
-                    }
-                    Occupied(mut entry) => {
-                        entry.get_mut().push(pid_u32);
-                    }
-                }
+    let mut pids_list: Vec<&u32> = system.procs.keys().collect();
+    pids_list.sort_unstable();
+    for pid in pids_list {
+        let mut ppid = system.procs[pid].get_ppid();
+        // Re-parent processes whose parent exited before it could be read
+        if ppid != 0 && !system.procs.contains_key(&ppid) {
+            ppid = 0;
+        }
+        match ppid_map.entry(ppid) {
+            Vacant(entry) => {
+                entry.insert(vec![*pid]);
+            }
+            Occupied(mut entry) => {
+                entry.get_mut().push(*pid);
             }
         }
     }
@@ -286,9 +311,17 @@ impl Process {
             name: String::new(),
             cpu_used: 0f32,
             mem_used: 0f32,
+            memory: ProcessMemory::default(),
+            io: ProcessIo::default(),
+            scheduling: ProcessScheduling::default(),
+            cpus_allowed: Vec::new(),
+            processor: 0,
+            starttime: 0,
             path: String::new(),
             user: String::new(),
             ppid: 0u32,
+            threads: Vec::new(),
+            sockets: Vec::new(),
         }
     }
     pub fn get_name(&self) -> &String {
@@ -300,6 +333,27 @@ impl Process {
     pub fn get_mem_used(&self) -> f32 {
         self.mem_used
     }
+    pub fn get_memory(&self) -> &ProcessMemory {
+        &self.memory
+    }
+    pub fn get_io(&self) -> &ProcessIo {
+        &self.io
+    }
+    pub fn get_scheduling(&self) -> &ProcessScheduling {
+        &self.scheduling
+    }
+    /// CPUs the process may run on, from `Cpus_allowed_list`.
+    pub fn get_cpus_allowed(&self) -> &Vec<usize> {
+        &self.cpus_allowed
+    }
+    /// CPU the process last ran on.
+    pub fn get_processor(&self) -> u32 {
+        self.processor
+    }
+    /// Start time in clock ticks after boot, which tells apart processes reusing a PID.
+    pub fn get_start_time(&self) -> u64 {
+        self.starttime
+    }
     pub fn get_path(&self) -> &String {
         &self.path
     }
@@ -312,40 +366,292 @@ impl Process {
     pub fn get_pid(&self) -> u32 {
         self.pid
     }
+    pub fn get_threads(&self) -> &Vec<Thread> {
+        &self.threads
+    }
+    pub fn get_sockets(&self) -> &Vec<Socket> {
+        &self.sockets
+    }
+}
+// Counters read during a refresh that become the baseline for the next one
+#[derive(Clone, Default)]
+struct RefreshSample {
+    elapsed_time: u64,
+    elapsed_secs: f32,
+    num_of_cpus: usize,
+    clock_tick: f32,
+    system_uptime: f32,
+    proc_cpu_times: HashMap<u32, (u64, u64)>,
+    thread_cpu_times: HashMap<u32, (u64, u64)>,
+    proc_io: HashMap<u32, (u64, ProcessIo)>,
+    proc_paths: HashMap<u32, CachedPath>,
+    user_names: UserNames,
+}
+// The exe link only changes on exec, which also renames the process
+#[derive(Clone)]
+struct CachedPath {
+    starttime: u64,
+    name: String,
+    path: String,
 }
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct System {
+    root: PathBuf,
     procs: HashMap<u32, Process>,
     cpu_used: f32,
-    mem_used: f32,
+    mem_info: MemoryInfo,
+    cpu_cores: Vec<CpuCoreUsage>,
+    cpu_topology: CpuTopology,
+    // Sampling state is left out, so a deserialized snapshot starts with a fresh baseline
+    #[cfg_attr(feature = "serde", serde(skip))]
+    prev_refresh: Option<Instant>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    prev_cpu_times: Option<CpuTimes>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    prev_core_times: HashMap<usize, CpuTimes>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    prev_sample: RefreshSample,
+    skipped_procs: usize,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    history: History,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    read_sockets: bool,
 }
 
 impl System {
     pub fn new() -> Self {
+        Self::with_root("/")
+    }
+    /// Reads `proc/` and `etc/` below `root`, e.g. a captured procfs snapshot.
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
         Self {
+            root: root.as_ref().to_path_buf(),
             procs: HashMap::new(),
             cpu_used: 0f32,
-            mem_used: 0f32,
+            mem_info: MemoryInfo::default(),
+            cpu_cores: Vec::new(),
+            cpu_topology: CpuTopology::default(),
+            prev_refresh: None,
+            prev_cpu_times: None,
+            prev_core_times: HashMap::new(),
+            prev_sample: RefreshSample::default(),
+            skipped_procs: 0,
+            history: History::default(),
+            read_sockets: false,
         }
     }
+    /// CPU usage is measured over the interval since the previous refresh; the
+    /// first refresh (and any newly seen process) falls back to lifetime averages.
     pub fn refresh_system_info(&mut self) -> Result<()> {
+        let root = self.root.as_path();
+        let refresh_time = Instant::now();
+        // Read everything that can fail first, so an error keeps the last good sample
+        let (cpu_times, core_times) = get_cpu_times(root)?;
+        let clock_tick = sysconf(SysconfVar::CLK_TCK)?.unwrap_or(100) as f32;
+        let system_uptime = get_system_uptime(root)?;
+        let mem_info = get_mem_info(root)?;
+        let pid_str_list = get_all_pids(root)?;
         self.procs.clear();
-        let pid_str_list = get_all_pids()?;
+        self.mem_info = mem_info;
+        let mut sample = RefreshSample {
+            elapsed_time: self
+                .prev_cpu_times
+                .map(|prev_cpu_times| cpu_times.total().saturating_sub(prev_cpu_times.total()))
+                .unwrap_or(0),
+            elapsed_secs: self
+                .prev_refresh
+                .map(|prev_refresh| (refresh_time - prev_refresh).as_secs_f32())
+                .unwrap_or(0f32),
+            num_of_cpus: core_times.len(),
+            clock_tick,
+            system_uptime,
+            user_names: take(&mut self.prev_sample.user_names),
+            ..Default::default()
+        };
+        sample.user_names.refresh(root);
+        // Mapping sockets to processes reads every descriptor of every process
+        let sockets = match self.read_sockets {
+            true => Some(get_all_sockets(root)),
+            false => None,
+        };
+        let mut skipped_procs = 0usize;
         for pid_str in pid_str_list {
-            let mut process_info = Process::new();
-            let pid = pid_str.parse::<u32>()?;
-            process_info.pid = pid;
-            process_info.name = get_proc_name(pid)?;
-            process_info.cpu_used = get_proc_cpu_usage(pid)?;
-            process_info.mem_used = get_proc_mem_usage(pid)?;
-            process_info.path = get_proc_path(pid)?;
-            process_info.user = get_proc_user(pid)?;
-            process_info.ppid = get_proc_ppid(pid)?;
-            self.procs.insert(pid, process_info);
+            let Ok(pid) = pid_str.parse::<u32>() else {
+                skipped_procs += 1;
+                continue;
+            };
+            // The process may exit between listing /proc and reading its files
+            match self.read_proc_info(pid, sockets.as_ref(), &mut sample) {
+                Ok(process_info) => {
+                    self.procs.insert(pid, process_info);
+                }
+                Err(_) => skipped_procs += 1,
+            }
         }
-        self.cpu_used = get_total_cpu_usage()?;
-        self.mem_used = get_total_mem_usage()?;
+        self.skipped_procs = skipped_procs;
+        let elapsed_time = sample.elapsed_time;
+        self.cpu_used = match self.prev_cpu_times {
+            Some(prev_cpu_times) if elapsed_time > 0 => {
+                let idle_delta = cpu_times.idle().saturating_sub(prev_cpu_times.idle());
+                100f32 - (idle_delta as f32 * 100f32) / elapsed_time as f32
+            }
+            _ if cpu_times.total() > 0 => {
+                100f32 - (cpu_times.idle() as f32 * 100f32) / cpu_times.total() as f32
+            }
+            _ => 0f32,
+        };
+        let mut core_ids: Vec<&usize> = core_times.keys().collect();
+        core_ids.sort_unstable();
+        self.cpu_cores = core_ids
+            .into_iter()
+            .map(|core_id| {
+                CpuCoreUsage::new(
+                    *core_id,
+                    &core_times[core_id],
+                    self.prev_core_times.get(core_id),
+                )
+            })
+            .collect();
+        self.cpu_topology = get_cpu_topology(root, sample.num_of_cpus);
+        self.prev_refresh = Some(refresh_time);
+        self.prev_cpu_times = Some(cpu_times);
+        self.prev_core_times = core_times;
+        self.prev_sample = sample;
+        self.record_history(SystemTime::now());
         Ok(())
     }
+    fn read_proc_info(
+        &self,
+        pid: u32,
+        sockets: Option<&HashMap<u64, Socket>>,
+        sample: &mut RefreshSample,
+    ) -> Result<Process> {
+        let root = self.root.as_path();
+        let mut process_info = Process::new();
+        process_info.pid = pid;
+        let proc_status = get_proc_status(root, pid)?;
+        process_info.name = proc_status.name;
+        let proc_stat = get_proc_stat(root, pid)?;
+        let (busy_time, starttime) = (proc_stat.busy_time, proc_stat.starttime);
+        process_info.scheduling = proc_stat.scheduling;
+        if let Some((policy, prio)) = get_proc_sched(root, pid) {
+            process_info.scheduling.set_sched(policy, prio);
+        }
+        process_info.processor = proc_stat.processor;
+        process_info.starttime = starttime;
+        process_info.cpu_used = self.get_cpu_usage_since(
+            self.prev_sample.proc_cpu_times.get(&pid),
+            (starttime, busy_time),
+            sample,
+        );
+        process_info.memory = get_proc_memory(root, pid, proc_status.memory);
+        let mem_total = self.mem_info.get_total();
+        if mem_total != 0 {
+            process_info.mem_used =
+                process_info.memory.get_rss() as f32 / mem_total as f32 * 100f32;
+        }
+        process_info.io = get_proc_io(root, pid).unwrap_or_default();
+        if let Some((prev_starttime, prev_io)) = self.prev_sample.proc_io.get(&pid) {
+            if *prev_starttime == starttime {
+                process_info.io.set_rates(prev_io, sample.elapsed_secs);
+            }
+        }
+        process_info.path = match self.prev_sample.proc_paths.get(&pid) {
+            Some(cached) if cached.starttime == starttime && cached.name == process_info.name => {
+                cached.path.clone()
+            }
+            _ => get_proc_path(root, pid),
+        };
+        process_info.user = sample.user_names.get_name(root, proc_status.uid);
+        process_info.ppid = proc_status.ppid;
+        process_info.cpus_allowed = proc_status.cpus_allowed;
+        for mut thread_sample in get_thread_samples(root, pid) {
+            let tid = thread_sample.thread.get_tid();
+            let cpu_times = (thread_sample.starttime, thread_sample.busy_time);
+            thread_sample.set_cpu_used(self.get_cpu_usage_since(
+                self.prev_sample.thread_cpu_times.get(&tid),
+                cpu_times,
+                sample,
+            ));
+            sample.thread_cpu_times.insert(tid, cpu_times);
+            process_info.threads.push(thread_sample.thread);
+        }
+        if let Some(sockets) = sockets {
+            process_info.sockets = get_proc_socket_inodes(root, pid)
+                .iter()
+                .filter_map(|inode| sockets.get(inode).cloned())
+                .collect();
+        }
+        sample.proc_cpu_times.insert(pid, (starttime, busy_time));
+        sample.proc_paths.insert(
+            pid,
+            CachedPath {
+                starttime,
+                name: process_info.name.clone(),
+                path: process_info.path.clone(),
+            },
+        );
+        sample
+            .proc_io
+            .insert(pid, (starttime, process_info.io.clone()));
+        Ok(process_info)
+    }
+    fn get_cpu_usage_since(
+        &self,
+        prev_cpu_times: Option<&(u64, u64)>,
+        (starttime, busy_time): (u64, u64),
+        sample: &RefreshSample,
+    ) -> f32 {
+        let elapsed_time = sample.elapsed_time;
+        match prev_cpu_times {
+            Some(&(prev_starttime, prev_busy_time))
+                if prev_starttime == starttime && elapsed_time > 0 =>
+            {
+                100f32 * busy_time.saturating_sub(prev_busy_time) as f32 / elapsed_time as f32
+            }
+            _ => get_proc_lifetime_cpu_usage(busy_time, starttime, sample),
+        }
+    }
+    pub(crate) fn record_history(&mut self, timestamp: SystemTime) {
+        let mut history = take(&mut self.history);
+        history.record(self, timestamp);
+        self.history = history;
+    }
+    pub fn get_history(&self) -> &History {
+        &self.history
+    }
+    pub fn set_history_duration(&mut self, duration: Duration) {
+        self.history.set_duration(duration);
+    }
+    /// Sockets of processes are only read while enabled, from the next refresh on.
+    pub fn set_read_sockets(&mut self, read_sockets: bool) {
+        self.read_sockets = read_sockets;
+    }
+    // The history so far, only the latest refresh is kept afterwards
+    pub(crate) fn take_history(&mut self) -> History {
+        replace(&mut self.history, History::new(Duration::ZERO))
+    }
+    // A copy of the latest refresh without the baseline kept for the next one, which
+    // holds counters of every process and thread and is only of use to `self`
+    pub(crate) fn snapshot(&mut self) -> System {
+        let prev_sample = take(&mut self.prev_sample);
+        let prev_core_times = take(&mut self.prev_core_times);
+        let mut snapshot = self.clone();
+        self.prev_sample = prev_sample;
+        self.prev_core_times = prev_core_times;
+        snapshot.prev_refresh = None;
+        snapshot.prev_cpu_times = None;
+        snapshot
+    }
+    /// Read on demand, as listing every descriptor of every process on each refresh is costly.
+    pub fn get_proc_open_files(&self, pid: u32) -> Result<Vec<OpenFile>> {
+        get_open_files(&self.root, pid)
+    }
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
     pub fn get_proc_info(&self, pid: &u32) -> Option<&Process> {
         self.procs.get(pid)
     }
@@ -353,7 +659,20 @@ impl System {
         self.cpu_used
     }
     pub fn get_total_mem_usage(&self) -> f32 {
-        self.mem_used
+        self.mem_info.get_used_percent()
+    }
+    pub fn get_mem_info(&self) -> &MemoryInfo {
+        &self.mem_info
+    }
+    pub fn get_cpu_cores(&self) -> &Vec<CpuCoreUsage> {
+        &self.cpu_cores
+    }
+    pub fn get_cpu_topology(&self) -> &CpuTopology {
+        &self.cpu_topology
+    }
+    /// Number of processes left out of the last refresh because they could not be read.
+    pub fn get_skipped_procs_count(&self) -> usize {
+        self.skipped_procs
     }
     pub fn get_procs_as_list(&mut self) -> Vec<(u32, Process)> {
         let proc_list: Vec<(u32, Process)> = self.procs.drain().collect();
@@ -371,7 +690,189 @@ pub fn print_proc_info(proc: &Process) {
     println!("Name:        {}", proc.name);
     println!("CPU used:    {}", proc.cpu_used);
     println!("Memory used: {}", proc.mem_used);
+    println!("RSS bytes:   {}", proc.memory.get_rss());
     println!("Path:        {}", proc.path);
     println!("User:        {}", proc.user);
     println!("Ppid:        {}", proc.ppid);
+    println!("Threads:     {}", proc.threads.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, FixtureProc, FIRST_UID};
+
+    fn refreshed_system(fixture: &Fixture) -> System {
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        system
+    }
+
+    fn child_pids(node: &ProcessTreeNode) -> Vec<u32> {
+        node.children
+            .iter()
+            .map(|child| child.proc_info.get_pid())
+            .collect()
+    }
+
+    #[test]
+    fn proc_status_is_read_in_one_pass() {
+        let fixture = Fixture::new().unwrap();
+        let mut proc = FixtureProc::new(42, 7);
+        proc.uid = FIRST_UID + 3;
+        fixture.write_proc(&proc).unwrap();
+        let proc_status = get_proc_status(fixture.get_root(), 42).unwrap();
+        assert_eq!(proc_status.name, "worker 42");
+        assert_eq!(proc_status.uid, FIRST_UID + 3);
+        assert_eq!(proc_status.ppid, 7);
+        assert_eq!(proc_status.cpus_allowed, vec![0, 1, 3]);
+        assert_eq!(proc_status.memory.get_rss(), 4096 * 1024);
+        assert_eq!(proc_status.memory.get_uss(), 1024 * 1024);
+        assert_eq!(proc_status.memory.get_shared(), 3072 * 1024);
+        assert_eq!(proc_status.memory.get_swap(), 8 * 1024);
+    }
+
+    #[test]
+    fn proc_stat_fields_follow_a_comm_with_spaces_and_parens() {
+        let fixture = Fixture::new().unwrap();
+        let mut proc = FixtureProc::new(42, 1);
+        proc.name = String::from("evil) (name 1 2");
+        proc.busy_time = (150, 50);
+        proc.starttime = 123456;
+        proc.nice = -5;
+        proc.processor = 3;
+        fixture.write_proc(&proc).unwrap();
+        let proc_stat = get_proc_stat(fixture.get_root(), 42).unwrap();
+        assert_eq!(proc_stat.busy_time, 200);
+        assert_eq!(proc_stat.starttime, 123456);
+        assert_eq!(proc_stat.processor, 3);
+        assert_eq!(proc_stat.scheduling.get_nice(), -5);
+        assert_eq!(proc_stat.scheduling.get_priority(), 15);
+    }
+
+    #[test]
+    fn scheduling_prefers_proc_sched_over_stat() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        fixture
+            .write(
+                "proc/2/sched",
+                "worker (2, #threads: 1)\n---\npolicy                                       :                    1\n\
+                 prio                                         :                   89\n",
+            )
+            .unwrap();
+        let system = refreshed_system(&fixture);
+        let scheduling = system.get_proc_info(&2).unwrap().get_scheduling();
+        assert!(scheduling.get_policy() == SchedulingPolicy::Fifo);
+        assert_eq!(scheduling.get_priority(), -11);
+        let scheduling = system.get_proc_info(&3).unwrap().get_scheduling();
+        assert!(scheduling.get_policy() == SchedulingPolicy::Other);
+        assert_eq!(scheduling.get_priority(), 20);
+    }
+
+    #[test]
+    fn refresh_reads_every_fixture_process() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(20).unwrap();
+        let mut system = refreshed_system(&fixture);
+        assert_eq!(system.get_skipped_procs_count(), 0);
+        let proc_info = system.get_proc_info(&7).unwrap();
+        assert_eq!(proc_info.get_user(), "user7");
+        assert_eq!(proc_info.get_path(), "/usr/bin/worker");
+        assert_eq!(system.get_procs_as_list().len(), 20);
+    }
+
+    #[test]
+    fn failed_refresh_keeps_the_last_sample() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        let mut system = refreshed_system(&fixture);
+        let prev_total = system.prev_cpu_times.unwrap().total();
+        fs::remove_file(fixture.get_root().join("proc/stat")).unwrap();
+        assert!(system.refresh_system_info().is_err());
+        assert_eq!(system.procs.len(), 3);
+        assert_eq!(system.prev_cpu_times.unwrap().total(), prev_total);
+        assert!(system.prev_sample.proc_cpu_times.contains_key(&2));
+    }
+
+    #[test]
+    fn refresh_skips_half_written_processes() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        // 8 lost its stat, 9 only has a status and 10 is already empty
+        fixture.write_proc(&FixtureProc::new(8, 1)).unwrap();
+        fs::remove_file(fixture.get_root().join("proc/8/stat")).unwrap();
+        fixture
+            .write("proc/9/status", "Name:\tworker 9\nPPid:\t1\n")
+            .unwrap();
+        fs::create_dir_all(fixture.get_root().join("proc/10")).unwrap();
+        let mut system = refreshed_system(&fixture);
+        assert_eq!(system.get_skipped_procs_count(), 3);
+        assert!(system.get_proc_info(&8).is_none());
+        assert!(system.get_proc_info(&9).is_none());
+        assert!(system.get_proc_info(&10).is_none());
+        assert_eq!(system.get_procs_as_list().len(), 3);
+    }
+
+    #[test]
+    fn snapshots_leave_out_the_refresh_baseline() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        let mut system = refreshed_system(&fixture);
+        let snapshot = system.snapshot();
+        assert_eq!(snapshot.procs.len(), 3);
+        assert!(snapshot.prev_cpu_times.is_none());
+        assert!(snapshot.prev_core_times.is_empty());
+        assert!(snapshot.prev_sample.proc_cpu_times.is_empty());
+        assert!(snapshot.prev_sample.thread_cpu_times.is_empty());
+        assert_eq!(system.prev_core_times.len(), 4);
+        assert_eq!(system.prev_sample.proc_cpu_times.len(), 3);
+    }
+
+    #[test]
+    fn refresh_without_passwd_names_users_by_uid() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        fs::remove_file(fixture.get_root().join("etc/passwd")).unwrap();
+        let system = refreshed_system(&fixture);
+        assert_eq!(system.get_skipped_procs_count(), 0);
+        assert_eq!(system.get_proc_info(&2).unwrap().get_user(), "1002");
+    }
+
+    #[test]
+    fn sockets_are_only_read_when_enabled() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        let mut system = refreshed_system(&fixture);
+        assert!(system.get_proc_info(&2).unwrap().get_sockets().is_empty());
+        system.set_read_sockets(true);
+        system.refresh_system_info().unwrap();
+        let sockets = system.get_proc_info(&2).unwrap().get_sockets();
+        assert_eq!(sockets.len(), 1);
+        assert_eq!(sockets[0].get_inode(), 100_002);
+        assert_eq!(sockets[0].get_local_address(), "127.0.0.1:2");
+        assert!(sockets[0].get_state() == SocketState::Listen);
+        system.set_read_sockets(false);
+        system.refresh_system_info().unwrap();
+        assert!(system.get_proc_info(&2).unwrap().get_sockets().is_empty());
+    }
+
+    #[test]
+    fn process_tree_reparents_orphans_to_the_root() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        for (pid, ppid) in [(1, 0), (2, 1), (3, 2), (4, 1), (9, 8)] {
+            fixture.write_proc(&FixtureProc::new(pid, ppid)).unwrap();
+        }
+        let proc_tree = build_process_tree(&refreshed_system(&fixture));
+        let root = &proc_tree.root;
+        assert_eq!(root.proc_info.get_pid(), 0);
+        // 9 lost its parent 8 and hangs below the root instead
+        assert_eq!(child_pids(root), vec![1, 9]);
+        let init = &root.children[0];
+        assert_eq!(child_pids(init), vec![2, 4]);
+        assert_eq!(child_pids(&init.children[0]), vec![3]);
+        assert!(init.children[1].children.is_empty());
+        assert!(root.children[1].children.is_empty());
+    }
 }
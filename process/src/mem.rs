@@ -0,0 +1,208 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessMemory {
+    rss: u64,
+    pss: u64,
+    uss: u64,
+    shared: u64,
+    swap: u64,
+}
+
+impl ProcessMemory {
+    pub fn get_rss(&self) -> u64 {
+        self.rss
+    }
+    /// Zero when `smaps_rollup` could not be read (e.g. another user's process).
+    pub fn get_pss(&self) -> u64 {
+        self.pss
+    }
+    pub fn get_uss(&self) -> u64 {
+        self.uss
+    }
+    pub fn get_shared(&self) -> u64 {
+        self.shared
+    }
+    pub fn get_swap(&self) -> u64 {
+        self.swap
+    }
+}
+
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryInfo {
+    total: u64,
+    free: u64,
+    available: u64,
+    buffers: u64,
+    cached: u64,
+    shmem: u64,
+    slab_reclaimable: u64,
+    swap_total: u64,
+    swap_free: u64,
+    swap_cached: u64,
+}
+
+impl MemoryInfo {
+    pub fn get_total(&self) -> u64 {
+        self.total
+    }
+    pub fn get_free(&self) -> u64 {
+        self.free
+    }
+    pub fn get_available(&self) -> u64 {
+        self.available
+    }
+    pub fn get_used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+    pub fn get_buffers(&self) -> u64 {
+        self.buffers
+    }
+    pub fn get_cached(&self) -> u64 {
+        self.cached
+    }
+    pub fn get_shmem(&self) -> u64 {
+        self.shmem
+    }
+    pub fn get_slab_reclaimable(&self) -> u64 {
+        self.slab_reclaimable
+    }
+    pub fn get_swap_total(&self) -> u64 {
+        self.swap_total
+    }
+    pub fn get_swap_free(&self) -> u64 {
+        self.swap_free
+    }
+    pub fn get_swap_used(&self) -> u64 {
+        self.swap_total.saturating_sub(self.swap_free)
+    }
+    pub fn get_swap_cached(&self) -> u64 {
+        self.swap_cached
+    }
+    pub fn get_used_percent(&self) -> f32 {
+        if self.total == 0 {
+            return 0f32;
+        }
+        self.get_used() as f32 * 100f32 / self.total as f32
+    }
+}
+
+/// Parses the `<value> kB` part of a `Key: <value> kB` line into bytes.
+#[cfg(target_os = "linux")]
+pub(crate) fn parse_kb_value(value: &str) -> Option<u64> {
+    value
+        .split_whitespace()
+        .next()
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(|value| value * 1024)
+}
+
+/// Parses `Key: <value> kB` files such as `meminfo` and `smaps_rollup` into bytes.
+#[cfg(target_os = "linux")]
+fn parse_kb_fields(buffer: &str) -> HashMap<&str, u64> {
+    let mut fields: HashMap<&str, u64> = HashMap::new();
+    for line in buffer.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            if let Some(value) = parse_kb_value(value) {
+                fields.insert(key, value);
+            }
+        }
+    }
+    fields
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn get_mem_info(root: &Path) -> Result<MemoryInfo> {
+    let buffer = fs::read_to_string(root.join("proc/meminfo"))?;
+    let fields = parse_kb_fields(&buffer);
+    let field = |key: &str| fields.get(key).copied().unwrap_or(0);
+    let free = field("MemFree");
+    let buffers = field("Buffers");
+    let cached = field("Cached");
+    Ok(MemoryInfo {
+        total: field("MemTotal"),
+        free,
+        // Kernels before 3.14 do not report MemAvailable
+        available: fields
+            .get("MemAvailable")
+            .copied()
+            .unwrap_or(free + buffers + cached),
+        buffers,
+        cached,
+        shmem: field("Shmem"),
+        slab_reclaimable: field("SReclaimable"),
+        swap_total: field("SwapTotal"),
+        swap_free: field("SwapFree"),
+        swap_cached: field("SwapCached"),
+    })
+}
+
+#[cfg(target_os = "linux")]
+impl ProcessMemory {
+    /// Takes a memory field of `/proc/<pid>/status`, other keys are ignored.
+    pub(crate) fn set_status_field(&mut self, key: &str, value: u64) {
+        match key {
+            "VmRSS" => self.rss = value,
+            "RssAnon" => self.uss = value,
+            "RssFile" | "RssShmem" => self.shared += value,
+            "VmSwap" => self.swap = value,
+            _ => {}
+        }
+    }
+}
+
+/// Refines the memory read from `status` with `smaps_rollup` when it can be read.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_proc_memory(
+    root: &Path,
+    pid: u32,
+    mut proc_memory: ProcessMemory,
+) -> ProcessMemory {
+    let proc_dir = root.join("proc").join(pid.to_string());
+    // smaps_rollup needs ptrace read access, so it is only available for some processes
+    if let Ok(buffer) = fs::read_to_string(proc_dir.join("smaps_rollup")) {
+        let rollup_fields = parse_kb_fields(&buffer);
+        let rollup_field = |key: &str| rollup_fields.get(key).copied().unwrap_or(0);
+        proc_memory.pss = rollup_field("Pss");
+        proc_memory.uss = rollup_field("Private_Clean") + rollup_field("Private_Dirty");
+        proc_memory.shared = rollup_field("Shared_Clean") + rollup_field("Shared_Dirty");
+        proc_memory.swap = rollup_field("Swap");
+    }
+    proc_memory
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    #[test]
+    fn mem_info_uses_mem_available() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        let mem_info = get_mem_info(fixture.get_root()).unwrap();
+        assert_eq!(mem_info.get_total(), 16384000 * 1024);
+        assert_eq!(mem_info.get_available(), 12288000 * 1024);
+        assert_eq!(mem_info.get_used(), 4096000 * 1024);
+        assert_eq!(mem_info.get_used_percent(), 25f32);
+        assert_eq!(mem_info.get_swap_used(), 512000 * 1024);
+    }
+
+    #[test]
+    fn mem_available_falls_back_on_old_kernels() {
+        let fixture = Fixture::new().unwrap();
+        fixture
+            .write(
+                "proc/meminfo",
+                "MemTotal: 1000 kB\nMemFree: 100 kB\nBuffers: 50 kB\nCached: 250 kB\n",
+            )
+            .unwrap();
+        let mem_info = get_mem_info(fixture.get_root()).unwrap();
+        assert_eq!(mem_info.get_available(), 400 * 1024);
+    }
+}
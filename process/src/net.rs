@@ -0,0 +1,320 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
+
+// Set in the unix socket Flags column for listening sockets
+const UNIX_ACCEPT_CONNECTIONS: u32 = 0x10000;
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SocketProtocol {
+    Tcp,
+    Tcp6,
+    Udp,
+    Udp6,
+    Unix,
+}
+
+impl SocketProtocol {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SocketProtocol::Tcp => "tcp",
+            SocketProtocol::Tcp6 => "tcp6",
+            SocketProtocol::Udp => "udp",
+            SocketProtocol::Udp6 => "udp6",
+            SocketProtocol::Unix => "unix",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SocketState {
+    Established,
+    SynSent,
+    SynRecv,
+    FinWait1,
+    FinWait2,
+    TimeWait,
+    Close,
+    CloseWait,
+    LastAck,
+    Listen,
+    Closing,
+    Unconnected,
+    Unknown,
+}
+
+impl SocketState {
+    fn from_inet(protocol: SocketProtocol, state: u8) -> Self {
+        match (protocol, state) {
+            (SocketProtocol::Udp | SocketProtocol::Udp6, 0x07) => SocketState::Unconnected,
+            (_, 0x01) => SocketState::Established,
+            (_, 0x02) => SocketState::SynSent,
+            (_, 0x03) => SocketState::SynRecv,
+            (_, 0x04) => SocketState::FinWait1,
+            (_, 0x05) => SocketState::FinWait2,
+            (_, 0x06) => SocketState::TimeWait,
+            (_, 0x07) => SocketState::Close,
+            (_, 0x08) => SocketState::CloseWait,
+            (_, 0x09) => SocketState::LastAck,
+            (_, 0x0A) => SocketState::Listen,
+            (_, 0x0B) => SocketState::Closing,
+            _ => SocketState::Unknown,
+        }
+    }
+    /// Labels as printed by `ss`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SocketState::Established => "ESTAB",
+            SocketState::SynSent => "SYN-SENT",
+            SocketState::SynRecv => "SYN-RECV",
+            SocketState::FinWait1 => "FIN-WAIT-1",
+            SocketState::FinWait2 => "FIN-WAIT-2",
+            SocketState::TimeWait => "TIME-WAIT",
+            SocketState::Close => "CLOSE",
+            SocketState::CloseWait => "CLOSE-WAIT",
+            SocketState::LastAck => "LAST-ACK",
+            SocketState::Listen => "LISTEN",
+            SocketState::Closing => "CLOSING",
+            SocketState::Unconnected => "UNCONN",
+            SocketState::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Socket {
+    protocol: SocketProtocol,
+    state: SocketState,
+    local_address: String,
+    remote_address: String,
+    inode: u64,
+}
+
+impl Socket {
+    pub fn get_protocol(&self) -> SocketProtocol {
+        self.protocol
+    }
+    pub fn get_state(&self) -> SocketState {
+        self.state
+    }
+    /// `address:port` for inet sockets, the bound path (if any) for unix sockets.
+    pub fn get_local_address(&self) -> &String {
+        &self.local_address
+    }
+    pub fn get_remote_address(&self) -> &String {
+        &self.remote_address
+    }
+    pub fn get_inode(&self) -> u64 {
+        self.inode
+    }
+}
+
+// The kernel prints each 32 bit word of the address in host byte order
+#[cfg(target_os = "linux")]
+fn parse_inet_address(address: &str) -> Result<SocketAddr> {
+    let (ip, port) = address
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Malformed socket address {}", address))?;
+    let port = u16::from_str_radix(port, 16)?;
+    let mut words: Vec<[u8; 4]> = Vec::new();
+    for index in (0..ip.len()).step_by(8) {
+        let word = ip
+            .get(index..index + 8)
+            .ok_or_else(|| anyhow!("Malformed socket address {}", address))?;
+        words.push(u32::from_str_radix(word, 16)?.to_ne_bytes());
+    }
+    let octets: Vec<u8> = words.concat();
+    match octets.len() {
+        4 => Ok(SocketAddr::new(
+            Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3]).into(),
+            port,
+        )),
+        16 => {
+            let mut ipv6_octets = [0u8; 16];
+            ipv6_octets.copy_from_slice(&octets);
+            Ok(SocketAddr::new(Ipv6Addr::from(ipv6_octets).into(), port))
+        }
+        _ => Err(anyhow!("Malformed socket address {}", address)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn parse_inet_socket(protocol: SocketProtocol, line: &str) -> Option<Socket> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 10 {
+        return None;
+    }
+    Some(Socket {
+        protocol,
+        state: SocketState::from_inet(protocol, u8::from_str_radix(fields[3], 16).ok()?),
+        local_address: parse_inet_address(fields[1]).ok()?.to_string(),
+        remote_address: parse_inet_address(fields[2]).ok()?.to_string(),
+        inode: fields[9].parse::<u64>().ok()?,
+    })
+}
+
+/// Malformed lines are skipped, the rest of the table is still read.
+#[cfg(target_os = "linux")]
+fn get_inet_sockets(root: &Path, protocol: SocketProtocol) -> Result<Vec<Socket>> {
+    let buffer = fs::read_to_string(root.join("proc/net").join(protocol.as_str()))?;
+    Ok(buffer
+        .lines()
+        .skip(1)
+        .filter_map(|line| parse_inet_socket(protocol, line))
+        .collect())
+}
+
+// Num, RefCount, Protocol, Flags, Type, St and Inode, followed by the optional path
+#[cfg(target_os = "linux")]
+const UNIX_SOCKET_FIELDS: usize = 7;
+
+#[cfg(target_os = "linux")]
+fn parse_unix_socket(line: &str) -> Option<Socket> {
+    let mut fields: Vec<&str> = Vec::with_capacity(UNIX_SOCKET_FIELDS);
+    let mut rest = line.trim_start();
+    while fields.len() < UNIX_SOCKET_FIELDS && !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    if fields.len() < UNIX_SOCKET_FIELDS {
+        return None;
+    }
+    let flags = u32::from_str_radix(fields[3], 16).ok()?;
+    let state = if flags & UNIX_ACCEPT_CONNECTIONS != 0 {
+        SocketState::Listen
+    } else if fields[5] == "03" {
+        SocketState::Established
+    } else {
+        SocketState::Unconnected
+    };
+    Some(Socket {
+        protocol: SocketProtocol::Unix,
+        state,
+        // The path is the rest of the line and may contain spaces
+        local_address: rest.to_string(),
+        remote_address: String::new(),
+        inode: fields[6].parse::<u64>().ok()?,
+    })
+}
+
+/// Malformed lines are skipped, the rest of the table is still read.
+#[cfg(target_os = "linux")]
+fn get_unix_sockets(root: &Path) -> Result<Vec<Socket>> {
+    let buffer = fs::read_to_string(root.join("proc/net/unix"))?;
+    Ok(buffer
+        .lines()
+        .skip(1)
+        .filter_map(parse_unix_socket)
+        .collect())
+}
+
+/// All sockets of the network namespace, keyed by inode. Missing tables are skipped.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_all_sockets(root: &Path) -> HashMap<u64, Socket> {
+    let mut sockets: HashMap<u64, Socket> = HashMap::new();
+    for protocol in [
+        SocketProtocol::Tcp,
+        SocketProtocol::Tcp6,
+        SocketProtocol::Udp,
+        SocketProtocol::Udp6,
+    ] {
+        for socket in get_inet_sockets(root, protocol).unwrap_or_default() {
+            sockets.insert(socket.inode, socket);
+        }
+    }
+    for socket in get_unix_sockets(root).unwrap_or_default() {
+        sockets.insert(socket.inode, socket);
+    }
+    sockets
+}
+
+/// Inodes of the sockets held open by `pid`, empty if its fd directory is not readable.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_proc_socket_inodes(root: &Path, pid: u32) -> Vec<u64> {
+    let mut inodes: Vec<u64> = Vec::new();
+    let Ok(fd_dir) = fs::read_dir(root.join("proc").join(pid.to_string()).join("fd")) else {
+        return inodes;
+    };
+    for fd in fd_dir.flatten() {
+        if let Ok(target) = fs::read_link(fd.path()) {
+            if let Some(inode) = target
+                .to_str()
+                .and_then(|target| target.strip_prefix("socket:["))
+                .and_then(|target| target.strip_suffix(']'))
+                .and_then(|inode| inode.parse::<u64>().ok())
+            {
+                inodes.push(inode);
+            }
+        }
+    }
+    inodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    const TCP_HEADER: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when \
+                              retrnsmt   uid  timeout inode\n";
+
+    #[test]
+    fn malformed_lines_only_drop_themselves() {
+        let fixture = Fixture::new().unwrap();
+        fixture
+            .write(
+                "proc/net/tcp",
+                &format!(
+                    "{}   0: 0100007F:0016 00000000:0000 0A 00000000:00000000 00:00000000 \
+                     00000000     0        0 1001 1 0000000000000000 100 0 0 10 0\n\
+                     \x20  1: 0100007F:zzzz 00000000:0000 0A 00000000:00000000 00:00000000 \
+                     00000000     0        0 1002 1 0000000000000000 100 0 0 10 0\n\
+                     \x20  2: 0100007F:1F90 0200007F:C350 01 00000000:00000000 00:00000000 \
+                     00000000  1000        0 1003 1 0000000000000000 20 4 30 10 -1\n",
+                    TCP_HEADER
+                ),
+            )
+            .unwrap();
+        fixture
+            .write(
+                "proc/net/unix",
+                "Num       RefCount Protocol Flags    Type St Inode Path\n\
+                 0000000000000000: 00000002 00000000 00010000 0001 01  2001 /run/a.sock\n\
+                 0000000000000000: 00000002 00000000 0001XXXX 0001 01  2002 /run/b.sock\n\
+                 0000000000000000: 00000003 00000000 00000000 0001 03  2003\n",
+            )
+            .unwrap();
+        let sockets = get_all_sockets(fixture.get_root());
+        let mut inodes: Vec<u64> = sockets.keys().copied().collect();
+        inodes.sort_unstable();
+        assert_eq!(inodes, vec![1001, 1003, 2001, 2003]);
+        assert_eq!(sockets[&1001].get_local_address(), "127.0.0.1:22");
+        assert!(sockets[&1001].get_state() == SocketState::Listen);
+        assert_eq!(sockets[&1003].get_remote_address(), "127.0.0.2:50000");
+        assert!(sockets[&1003].get_state() == SocketState::Established);
+        assert!(sockets[&2003].get_local_address().is_empty());
+        assert!(sockets[&2003].get_state() == SocketState::Established);
+    }
+
+    #[test]
+    fn unix_socket_paths_keep_their_spaces() {
+        let socket = parse_unix_socket(
+            "0000000000000000: 00000002 00000000 00010000 0001 01 31337 /tmp/my app/ctl sock",
+        )
+        .unwrap();
+        assert_eq!(socket.get_inode(), 31337);
+        assert_eq!(socket.get_local_address(), "/tmp/my app/ctl sock");
+        assert!(socket.get_state() == SocketState::Listen);
+        let socket =
+            parse_unix_socket("0000000000000000: 00000002 00000000 00000000 0002 07 42 @abstract")
+                .unwrap();
+        assert_eq!(socket.get_local_address(), "@abstract");
+        assert!(socket.get_state() == SocketState::Unconnected);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::{Filter, Process, System};
+use std::fmt::Write;
+
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+// Backslash, double quote and line feed must be escaped in label values
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_family(output: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(output, "# TYPE {} {}", name, metric_type);
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+}
+
+fn write_proc_family<F>(
+    output: &mut String,
+    procs: &[(String, &Process)],
+    (name, metric_type, help): (&str, &str, &str),
+    value: F,
+) where
+    F: Fn(&Process) -> String,
+{
+    write_family(output, name, metric_type, help);
+    let sample_name = match metric_type {
+        "counter" => format!("{}_total", name),
+        _ => name.to_string(),
+    };
+    for (labels, proc_info) in procs {
+        let _ = writeln!(output, "{}{{{}}} {}", sample_name, labels, value(proc_info));
+    }
+}
+
+/// Renders `system` in the OpenMetrics text format.
+///
+/// Per-process series are labelled by pid, name and user and are only written for
+/// processes matching `allowlist`, so that the number of series stays bounded.
+/// Without an allowlist only system-wide metrics are written.
+pub fn encode_openmetrics(system: &System, allowlist: Option<&Filter>) -> String {
+    let mut output = String::new();
+    write_family(
+        &mut output,
+        "forge_view_cpu_usage_percent",
+        "gauge",
+        "CPU usage of all cores since the previous refresh.",
+    );
+    let _ = writeln!(output, "forge_view_cpu_usage_percent {}", system.cpu_used);
+    write_family(
+        &mut output,
+        "forge_view_cpu_core_usage_percent",
+        "gauge",
+        "CPU usage of each core since the previous refresh.",
+    );
+    for core in system.cpu_cores.iter() {
+        let _ = writeln!(
+            output,
+            "forge_view_cpu_core_usage_percent{{cpu=\"{}\"}} {}",
+            core.get_id(),
+            core.get_total_usage()
+        );
+    }
+    write_family(
+        &mut output,
+        "forge_view_memory_usage_percent",
+        "gauge",
+        "Memory in use, excluding reclaimable caches.",
+    );
+    let _ = writeln!(
+        output,
+        "forge_view_memory_usage_percent {}",
+        system.mem_info.get_used_percent()
+    );
+    for (name, help, value) in [
+        (
+            "forge_view_memory_total_bytes",
+            "Total usable memory.",
+            system.mem_info.get_total(),
+        ),
+        (
+            "forge_view_memory_available_bytes",
+            "Memory available for new allocations without swapping.",
+            system.mem_info.get_available(),
+        ),
+        (
+            "forge_view_swap_used_bytes",
+            "Swap space in use.",
+            system.mem_info.get_swap_used(),
+        ),
+    ] {
+        write_family(&mut output, name, "gauge", help);
+        let _ = writeln!(output, "{} {}", name, value);
+    }
+    write_family(
+        &mut output,
+        "forge_view_processes",
+        "gauge",
+        "Processes read on the last refresh.",
+    );
+    let _ = writeln!(output, "forge_view_processes {}", system.procs.len());
+
+    if let Some(allowlist) = allowlist {
+        let mut pids: Vec<&u32> = system.procs.keys().collect();
+        pids.sort_unstable();
+        let procs: Vec<(String, &Process)> = pids
+            .into_iter()
+            .map(|pid| &system.procs[pid])
+            .filter(|proc_info| allowlist.matches(proc_info))
+            .map(|proc_info| {
+                let labels = format!(
+                    "pid=\"{}\",name=\"{}\",user=\"{}\"",
+                    proc_info.pid,
+                    escape_label_value(&proc_info.name),
+                    escape_label_value(&proc_info.user)
+                );
+                (labels, proc_info)
+            })
+            .collect();
+        write_proc_family(
+            &mut output,
+            &procs,
+            (
+                "forge_view_process_cpu_usage_percent",
+                "gauge",
+                "CPU usage of the process since the previous refresh.",
+            ),
+            |proc_info| proc_info.cpu_used.to_string(),
+        );
+        write_proc_family(
+            &mut output,
+            &procs,
+            (
+                "forge_view_process_memory_usage_percent",
+                "gauge",
+                "Resident memory of the process relative to total memory.",
+            ),
+            |proc_info| proc_info.mem_used.to_string(),
+        );
+        write_proc_family(
+            &mut output,
+            &procs,
+            (
+                "forge_view_process_resident_memory_bytes",
+                "gauge",
+                "Resident set size of the process.",
+            ),
+            |proc_info| proc_info.memory.get_rss().to_string(),
+        );
+        write_proc_family(
+            &mut output,
+            &procs,
+            (
+                "forge_view_process_threads",
+                "gauge",
+                "Threads of the process.",
+            ),
+            |proc_info| proc_info.threads.len().to_string(),
+        );
+        write_proc_family(
+            &mut output,
+            &procs,
+            (
+                "forge_view_process_read_bytes",
+                "counter",
+                "Bytes the process read from storage.",
+            ),
+            |proc_info| proc_info.io.get_read_bytes().to_string(),
+        );
+        write_proc_family(
+            &mut output,
+            &procs,
+            (
+                "forge_view_process_written_bytes",
+                "counter",
+                "Bytes the process wrote to storage.",
+            ),
+            |proc_info| proc_info.io.get_write_bytes().to_string(),
+        );
+    }
+    output.push_str("# EOF\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn fixture_system(fixture: &Fixture) -> System {
+        fixture.write_procs(3).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        system
+    }
+
+    #[test]
+    fn system_metrics_are_written_without_an_allowlist() {
+        let fixture = Fixture::new().unwrap();
+        let output = encode_openmetrics(&fixture_system(&fixture), None);
+        assert!(output.starts_with(
+            "# TYPE forge_view_cpu_usage_percent gauge\n\
+             # HELP forge_view_cpu_usage_percent CPU usage of all cores since the previous refresh.\n"
+        ));
+        assert!(output.contains("\nforge_view_cpu_core_usage_percent{cpu=\"3\"} "));
+        assert!(output.contains("\nforge_view_memory_total_bytes 16777216000\n"));
+        assert!(output.contains("\nforge_view_memory_available_bytes 12582912000\n"));
+        assert!(output.contains("\nforge_view_processes 3\n"));
+        assert!(!output.contains("forge_view_process_"));
+        assert!(output.ends_with("\n# EOF\n"));
+        assert_eq!(output.matches("# EOF").count(), 1);
+    }
+
+    #[test]
+    fn process_metrics_are_limited_to_the_allowlist() {
+        let fixture = Fixture::new().unwrap();
+        let mut system = fixture_system(&fixture);
+        system.procs.get_mut(&2).unwrap().name = String::from("say \"hi\"\\\nnow");
+        let allowlist = Filter::parse("pid >= 2").unwrap();
+        let output = encode_openmetrics(&system, Some(&allowlist));
+        assert!(output.contains(
+            "\nforge_view_process_resident_memory_bytes{pid=\"2\",name=\"say \\\"hi\\\"\\\\\\nnow\",user=\"user2\"} 4194304\n"
+        ));
+        assert!(output.contains(
+            "forge_view_process_threads{pid=\"3\",name=\"worker 3\",user=\"user3\"} 4\n"
+        ));
+        assert!(!output.contains("pid=\"1\""));
+        // Counter samples carry the _total suffix, their family does not
+        assert!(output.contains("\n# TYPE forge_view_process_read_bytes counter\n"));
+        assert!(output.contains("\nforge_view_process_read_bytes_total{pid=\"3\","));
+        assert!(output.contains("\nforge_view_process_written_bytes_total{pid=\"2\","));
+        assert!(!output.contains("forge_view_process_cpu_usage_percent_total"));
+        assert!(output.ends_with("\n# EOF\n"));
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{History, System, SystemSample};
+use anyhow::{anyhow, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const RECORDING_MAGIC: &[u8; 4] = b"FVRC";
+pub const RECORDING_VERSION: u32 = 1;
+const HEADER_LEN: u64 = 8;
+// Timestamp in milliseconds, total CPU and memory usage, payload length
+const FRAME_HEADER_LEN: usize = 8 + 4 + 4 + 4;
+
+// Layout: magic and version, then frames of a fixed header followed by the
+// deflate compressed JSON of one `System`. Frames are only ever appended, and
+// a frame cut short by a crash while recording is ignored when reading.
+
+/// Appends snapshots to a recording, creating the file if needed.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let file_len = file.metadata()?.len();
+        if file_len == 0 {
+            let mut header = RECORDING_MAGIC.to_vec();
+            header.extend_from_slice(&RECORDING_VERSION.to_le_bytes());
+            file.write_all(&header)?;
+        } else {
+            let mut reader = BufReader::new(&file);
+            read_header(&mut reader)?;
+            let (_, frames_end) = read_frames(&mut reader)?;
+            // Drop a frame left incomplete by an interrupted recording
+            if frames_end < file_len {
+                file.set_len(frames_end)?;
+            }
+        }
+        Ok(Self { file })
+    }
+    pub fn record(&mut self, system: &System) -> Result<()> {
+        self.record_at(system, SystemTime::now())
+    }
+    fn record_at(&mut self, system: &System, timestamp: SystemTime) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        serde_json::to_writer(&mut encoder, system)?;
+        let payload = encoder.finish()?;
+        let timestamp = timestamp.duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+        frame.extend_from_slice(&timestamp.to_le_bytes());
+        frame.extend_from_slice(&system.get_total_cpu_usage().to_le_bytes());
+        frame.extend_from_slice(&system.get_total_mem_usage().to_le_bytes());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        // Written in one call so an interruption leaves at most one partial frame
+        self.file.write_all(&frame)?;
+        Ok(())
+    }
+}
+
+struct RecordedFrame {
+    offset: u64,
+    len: u32,
+    sample: SystemSample,
+}
+
+/// Index of a recording; snapshots are decoded on demand.
+pub struct Recording {
+    file: File,
+    frames: Vec<RecordedFrame>,
+    // Samples up to the snapshot at `history_index`, extended while stepping forward
+    history: History,
+    history_index: Option<usize>,
+}
+
+impl Recording {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        read_header(&mut reader)?;
+        let (frames, _) = read_frames(&mut reader)?;
+        Ok(Self {
+            file: reader.into_inner(),
+            frames,
+            history: History::default(),
+            history_index: None,
+        })
+    }
+    pub fn set_history_duration(&mut self, duration: Duration) {
+        self.history = History::new(duration);
+        self.history_index = None;
+    }
+    /// Samples of the snapshots recorded within the history duration up to the one last
+    /// returned by [`Recording::get_snapshot`].
+    pub fn get_history(&self) -> &History {
+        &self.history
+    }
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    pub fn get_timestamp(&self, index: usize) -> Option<SystemTime> {
+        self.frames
+            .get(index)
+            .map(|frame| frame.sample.get_timestamp())
+    }
+    /// Also updates the history to end at this snapshot. Stepping forward only decodes the
+    /// snapshots after the previous one, jumping backwards decodes those within the history
+    /// duration again.
+    pub fn get_snapshot(&mut self, index: usize) -> Result<System> {
+        let system = self.decode_snapshot(index)?;
+        let timestamp = self.frames[index].sample.get_timestamp();
+        let first_kept = self.get_first_kept(index);
+        let first_missing = match self.history_index {
+            Some(history_index) if history_index == index => return Ok(system),
+            Some(history_index) if history_index < index && history_index + 1 >= first_kept => {
+                history_index + 1
+            }
+            _ => {
+                self.history.clear();
+                first_kept
+            }
+        };
+        // Forget the history while it is incomplete, in case decoding fails
+        self.history_index = None;
+        for prev_index in first_missing..index {
+            let prev_system = self.decode_snapshot(prev_index)?;
+            self.history
+                .record(&prev_system, self.frames[prev_index].sample.get_timestamp());
+        }
+        self.history.record(&system, timestamp);
+        self.history_index = Some(index);
+        Ok(system)
+    }
+    // First snapshot recorded within the history duration before the one at `index`
+    fn get_first_kept(&self, index: usize) -> usize {
+        let timestamp = self.frames[index].sample.get_timestamp();
+        let duration = self.history.get_duration();
+        self.frames[..index]
+            .iter()
+            .position(|frame| {
+                timestamp
+                    .duration_since(frame.sample.get_timestamp())
+                    .map(|age| age <= duration)
+                    .unwrap_or(true)
+            })
+            .unwrap_or(index)
+    }
+    fn decode_snapshot(&self, index: usize) -> Result<System> {
+        let frame = self
+            .frames
+            .get(index)
+            .ok_or_else(|| anyhow!("No snapshot {} in recording", index))?;
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(frame.offset))?;
+        let decoder = DeflateDecoder::new(file.take(frame.len as u64));
+        Ok(serde_json::from_reader(decoder)?)
+    }
+}
+
+// Returns the complete frames and the offset where they end
+fn read_frames<R: Read>(reader: &mut R) -> Result<(Vec<RecordedFrame>, u64)> {
+    let mut frames: Vec<RecordedFrame> = Vec::new();
+    let mut offset = HEADER_LEN;
+    let mut frame_header = [0u8; FRAME_HEADER_LEN];
+    loop {
+        match reader.read_exact(&mut frame_header) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+        let timestamp = u64::from_le_bytes(frame_header[0..8].try_into()?);
+        let cpu_used = f32::from_le_bytes(frame_header[8..12].try_into()?);
+        let mem_used = f32::from_le_bytes(frame_header[12..16].try_into()?);
+        let len = u32::from_le_bytes(frame_header[16..20].try_into()?);
+        let payload_len = io::copy(&mut reader.take(len as u64), &mut io::sink())?;
+        if payload_len < len as u64 {
+            break;
+        }
+        frames.push(RecordedFrame {
+            offset: offset + FRAME_HEADER_LEN as u64,
+            len,
+            sample: SystemSample::new(
+                UNIX_EPOCH + Duration::from_millis(timestamp),
+                cpu_used,
+                mem_used,
+            ),
+        });
+        offset += FRAME_HEADER_LEN as u64 + len as u64;
+    }
+    Ok((frames, offset))
+}
+
+fn read_header<R: Read>(reader: &mut R) -> Result<()> {
+    let mut header = [0u8; HEADER_LEN as usize];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| anyhow!("Not a forge_view recording"))?;
+    if &header[0..4] != RECORDING_MAGIC {
+        return Err(anyhow!("Not a forge_view recording"));
+    }
+    let version = u32::from_le_bytes(header[4..8].try_into()?);
+    if version != RECORDING_VERSION {
+        return Err(anyhow!(
+            "Unsupported recording version {}, expected {}",
+            version,
+            RECORDING_VERSION
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use std::collections::VecDeque;
+
+    // Snapshots 10 seconds apart, replayed with a history of 15 seconds
+    fn recording(fixture: &Fixture, snapshots: u64) -> Recording {
+        fixture.write_procs(3).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        let path = fixture.get_root().join("recording");
+        let mut recorder = Recorder::open(&path).unwrap();
+        for snapshot in 0..snapshots {
+            let timestamp = UNIX_EPOCH + Duration::from_secs(1000 + snapshot * 10);
+            recorder.record_at(&system, timestamp).unwrap();
+        }
+        let mut recording = Recording::open(&path).unwrap();
+        recording.set_history_duration(Duration::from_secs(15));
+        recording
+    }
+
+    fn history_secs(recording: &Recording) -> Vec<u64> {
+        recording
+            .get_history()
+            .get_system_samples()
+            .iter()
+            .map(|sample| {
+                sample
+                    .get_timestamp()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap()
+                    .as_secs()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn history_holds_process_samples_within_its_duration() {
+        let fixture = Fixture::new().unwrap();
+        let mut recording = recording(&fixture, 4);
+        assert_eq!(recording.len(), 4);
+        recording.get_snapshot(3).unwrap();
+        assert_eq!(history_secs(&recording), vec![1020, 1030]);
+        let proc_samples = recording.get_history().get_proc_samples(2);
+        assert_eq!(proc_samples.map(VecDeque::len), Some(2));
+        // Stepping back starts over
+        recording.get_snapshot(0).unwrap();
+        assert_eq!(history_secs(&recording), vec![1000]);
+    }
+
+    #[test]
+    fn stepping_forward_extends_the_history() {
+        let fixture = Fixture::new().unwrap();
+        let mut recording = recording(&fixture, 4);
+        for index in 0..3 {
+            recording.get_snapshot(index).unwrap();
+        }
+        assert_eq!(history_secs(&recording), vec![1010, 1020]);
+        assert_eq!(
+            recording
+                .get_history()
+                .get_proc_samples(1)
+                .map(VecDeque::len),
+            Some(2)
+        );
+    }
+
+    // Overwrites the payload of one snapshot, so decoding it again fails
+    fn damage_snapshot(fixture: &Fixture, recording: &Recording, index: usize) {
+        let frame = &recording.frames[index];
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(fixture.get_root().join("recording"))
+            .unwrap();
+        file.seek(SeekFrom::Start(frame.offset)).unwrap();
+        file.write_all(&vec![0xff; frame.len as usize]).unwrap();
+    }
+
+    #[test]
+    fn stepping_forward_only_decodes_the_new_snapshot() {
+        let fixture = Fixture::new().unwrap();
+        let mut recording = recording(&fixture, 4);
+        for index in 0..3 {
+            recording.get_snapshot(index).unwrap();
+        }
+        for index in 0..3 {
+            damage_snapshot(&fixture, &recording, index);
+        }
+        recording.get_snapshot(3).unwrap();
+        recording.get_snapshot(3).unwrap();
+        assert_eq!(history_secs(&recording), vec![1020, 1030]);
+        assert!(recording.get_snapshot(2).is_err());
+    }
+
+    #[test]
+    fn jumping_back_rebuilds_the_history() {
+        let fixture = Fixture::new().unwrap();
+        let mut recording = recording(&fixture, 4);
+        recording.get_snapshot(3).unwrap();
+        recording.get_snapshot(2).unwrap();
+        assert_eq!(history_secs(&recording), vec![1010, 1020]);
+        assert_eq!(
+            recording
+                .get_history()
+                .get_proc_samples(1)
+                .map(VecDeque::len),
+            Some(2)
+        );
+        damage_snapshot(&fixture, &recording, 1);
+        recording.get_snapshot(3).unwrap();
+        assert!(recording.get_snapshot(2).is_err());
+        // The history left incomplete by the failed jump is rebuilt on the next one
+        recording.get_snapshot(3).unwrap();
+        assert_eq!(history_secs(&recording), vec![1020, 1030]);
+    }
+
+    #[test]
+    fn partial_frames_are_ignored() {
+        let fixture = Fixture::new().unwrap();
+        recording(&fixture, 2);
+        let path = fixture.get_root().join("recording");
+        let file = OpenOptions::new().append(true).open(&path).unwrap();
+        let file_len = file.metadata().unwrap().len();
+        file.set_len(file_len - 5).unwrap();
+        assert_eq!(Recording::open(&path).unwrap().len(), 1);
+        // Recording again drops the partial frame first
+        drop(Recorder::open(&path).unwrap());
+        assert_eq!(Recording::open(&path).unwrap().len(), 1);
+    }
+}
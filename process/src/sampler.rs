@@ -0,0 +1,260 @@
+#[cfg(feature = "agent")]
+use crate::AgentClient;
+use crate::{History, System};
+use anyhow::Result;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+enum SamplerCommand {
+    Pause,
+    Resume,
+    RefreshNow,
+    SetInterval(Duration),
+    SetReadSockets(bool),
+    Stop,
+}
+
+/// Where snapshots come from.
+pub enum SnapshotSource {
+    /// Reads `/proc` in this process.
+    Local(Box<System>),
+    /// Asks an agent, sharing its sampling with other viewers.
+    #[cfg(feature = "agent")]
+    Agent(AgentClient),
+}
+
+impl SnapshotSource {
+    pub fn next_snapshot(&mut self) -> Result<System> {
+        match self {
+            SnapshotSource::Local(system) => {
+                system.refresh_system_info()?;
+                Ok(system.snapshot())
+            }
+            #[cfg(feature = "agent")]
+            SnapshotSource::Agent(client) => client.snapshot(),
+        }
+    }
+    pub fn set_read_sockets(&mut self, read_sockets: bool) {
+        match self {
+            SnapshotSource::Local(system) => system.set_read_sockets(read_sockets),
+            #[cfg(feature = "agent")]
+            SnapshotSource::Agent(client) => client.set_read_sockets(read_sockets),
+        }
+    }
+    // The history so far, afterwards snapshots only carry the samples of their refresh
+    fn take_history(&mut self) -> History {
+        match self {
+            SnapshotSource::Local(system) => system.take_history(),
+            #[cfg(feature = "agent")]
+            SnapshotSource::Agent(client) => client.take_history(),
+        }
+    }
+}
+
+impl From<System> for SnapshotSource {
+    fn from(system: System) -> Self {
+        SnapshotSource::Local(Box::new(system))
+    }
+}
+
+/// Refreshes a `System` on its own thread and publishes a copy after every refresh. The
+/// history is kept here instead of in the copies, which only carry their refresh.
+pub struct Sampler {
+    commands: Sender<SamplerCommand>,
+    snapshots: Receiver<System>,
+    history: History,
+    paused: bool,
+    interval: Duration,
+    read_sockets: bool,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sampler {
+    /// The first snapshot is published one interval after spawning, so a local `system`
+    /// should already be refreshed. `on_snapshot` runs on the sampler thread after
+    /// each publish, e.g. to wake up a UI.
+    pub fn spawn<S, F>(source: S, interval: Duration, on_snapshot: F) -> Self
+    where
+        S: Into<SnapshotSource>,
+        F: Fn() + Send + 'static,
+    {
+        let mut source = source.into();
+        let history = source.take_history();
+        let (commands, command_receiver) = mpsc::channel();
+        let (snapshot_sender, snapshots) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            sampler_loop(
+                source,
+                interval,
+                command_receiver,
+                snapshot_sender,
+                on_snapshot,
+            )
+        });
+        Self {
+            commands,
+            snapshots,
+            history,
+            paused: false,
+            interval,
+            read_sockets: false,
+            handle: Some(handle),
+        }
+    }
+    /// Latest published snapshot, skipping older ones still queued. Their samples are
+    /// added to the history.
+    pub fn try_recv_latest(&mut self) -> Option<System> {
+        let mut latest: Option<System> = None;
+        for mut system in self.snapshots.try_iter() {
+            self.history.append(system.take_history());
+            latest = Some(system);
+        }
+        latest
+    }
+    pub fn get_history(&self) -> &History {
+        &self.history
+    }
+    pub fn pause(&mut self) {
+        self.paused = true;
+        let _ = self.commands.send(SamplerCommand::Pause);
+    }
+    pub fn resume(&mut self) {
+        self.paused = false;
+        let _ = self.commands.send(SamplerCommand::Resume);
+    }
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// Also works while paused.
+    pub fn refresh_now(&self) {
+        let _ = self.commands.send(SamplerCommand::RefreshNow);
+    }
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+    pub fn set_interval(&mut self, interval: Duration) {
+        self.interval = interval;
+        let _ = self.commands.send(SamplerCommand::SetInterval(interval));
+    }
+    /// Turning sockets on also refreshes right away, so they show up without waiting
+    /// for the interval.
+    pub fn set_read_sockets(&mut self, read_sockets: bool) {
+        if self.read_sockets != read_sockets {
+            self.read_sockets = read_sockets;
+            let _ = self
+                .commands
+                .send(SamplerCommand::SetReadSockets(read_sockets));
+        }
+    }
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        let _ = self.commands.send(SamplerCommand::Stop);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn sampler_loop<F: Fn()>(
+    mut source: SnapshotSource,
+    mut interval: Duration,
+    commands: Receiver<SamplerCommand>,
+    snapshots: Sender<System>,
+    on_snapshot: F,
+) {
+    let mut paused = false;
+    let mut next_refresh = Instant::now() + interval;
+    loop {
+        let now = Instant::now();
+        if !paused && now >= next_refresh {
+            if !publish_snapshot(&mut source, &snapshots, &on_snapshot) {
+                return;
+            }
+            next_refresh = now + interval;
+        }
+        let timeout = match paused {
+            true => interval,
+            false => next_refresh.saturating_duration_since(Instant::now()),
+        };
+        match commands.recv_timeout(timeout) {
+            Ok(SamplerCommand::Pause) => paused = true,
+            Ok(SamplerCommand::Resume) => {
+                paused = false;
+                next_refresh = Instant::now();
+            }
+            Ok(SamplerCommand::RefreshNow) => {
+                if !publish_snapshot(&mut source, &snapshots, &on_snapshot) {
+                    return;
+                }
+                next_refresh = Instant::now() + interval;
+            }
+            Ok(SamplerCommand::SetInterval(new_interval)) => {
+                interval = new_interval;
+                next_refresh = Instant::now() + interval;
+            }
+            Ok(SamplerCommand::SetReadSockets(read_sockets)) => {
+                source.set_read_sockets(read_sockets);
+                if read_sockets {
+                    if !publish_snapshot(&mut source, &snapshots, &on_snapshot) {
+                        return;
+                    }
+                    next_refresh = Instant::now() + interval;
+                }
+            }
+            Ok(SamplerCommand::Stop) | Err(RecvTimeoutError::Disconnected) => return,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+}
+
+// Returns false once the receiving side is gone
+fn publish_snapshot<F: Fn()>(
+    source: &mut SnapshotSource,
+    snapshots: &Sender<System>,
+    on_snapshot: &F,
+) -> bool {
+    let system = match source.next_snapshot() {
+        Ok(system) => system,
+        Err(err) => {
+            println!("Error: Process lib could not compute metrics: {}", err);
+            return true;
+        }
+    };
+    if snapshots.send(system).is_err() {
+        return false;
+    }
+    on_snapshot();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn history_is_kept_by_the_sampler_not_its_snapshots() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_procs(3).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        let mut sampler = Sampler::spawn(system, Duration::from_millis(10), || {});
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sampler.get_history().get_system_samples().len() < 4 && Instant::now() < deadline {
+            if let Some(system) = sampler.try_recv_latest() {
+                assert!(system.get_history().get_system_samples().is_empty());
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        let history = sampler.get_history();
+        assert!(history.get_system_samples().len() >= 4);
+        assert_eq!(
+            history.get_proc_samples(1).map(VecDeque::len),
+            Some(history.get_system_samples().len())
+        );
+    }
+}
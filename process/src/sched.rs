@@ -0,0 +1,170 @@
+use nix::libc;
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SchedulingPolicy {
+    Other,
+    Fifo,
+    RoundRobin,
+    Batch,
+    Idle,
+    Deadline,
+    Unknown,
+}
+
+impl SchedulingPolicy {
+    pub(crate) fn from_raw(policy: i32) -> Self {
+        match policy {
+            libc::SCHED_OTHER => SchedulingPolicy::Other,
+            libc::SCHED_FIFO => SchedulingPolicy::Fifo,
+            libc::SCHED_RR => SchedulingPolicy::RoundRobin,
+            libc::SCHED_BATCH => SchedulingPolicy::Batch,
+            libc::SCHED_IDLE => SchedulingPolicy::Idle,
+            // SCHED_DEADLINE has no libc constant
+            6 => SchedulingPolicy::Deadline,
+            _ => SchedulingPolicy::Unknown,
+        }
+    }
+    pub(crate) fn as_raw(&self) -> Option<i32> {
+        match self {
+            SchedulingPolicy::Other => Some(libc::SCHED_OTHER),
+            SchedulingPolicy::Fifo => Some(libc::SCHED_FIFO),
+            SchedulingPolicy::RoundRobin => Some(libc::SCHED_RR),
+            SchedulingPolicy::Batch => Some(libc::SCHED_BATCH),
+            SchedulingPolicy::Idle => Some(libc::SCHED_IDLE),
+            SchedulingPolicy::Deadline | SchedulingPolicy::Unknown => None,
+        }
+    }
+    /// Real-time policies take a static priority between 1 and 99.
+    pub fn is_realtime(&self) -> bool {
+        matches!(self, SchedulingPolicy::Fifo | SchedulingPolicy::RoundRobin)
+    }
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchedulingPolicy::Other => "SCHED_OTHER",
+            SchedulingPolicy::Fifo => "SCHED_FIFO",
+            SchedulingPolicy::RoundRobin => "SCHED_RR",
+            SchedulingPolicy::Batch => "SCHED_BATCH",
+            SchedulingPolicy::Idle => "SCHED_IDLE",
+            SchedulingPolicy::Deadline => "SCHED_DEADLINE",
+            SchedulingPolicy::Unknown => "UNKNOWN",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IoPriorityClass {
+    None,
+    RealTime,
+    BestEffort,
+    Idle,
+}
+
+impl IoPriorityClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IoPriorityClass::None => "none",
+            IoPriorityClass::RealTime => "realtime",
+            IoPriorityClass::BestEffort => "best-effort",
+            IoPriorityClass::Idle => "idle",
+        }
+    }
+}
+
+// Layout of the value used by ioprio_get(2) and ioprio_set(2)
+const IOPRIO_CLASS_SHIFT: i32 = 13;
+const IOPRIO_PRIO_MASK: i32 = (1 << IOPRIO_CLASS_SHIFT) - 1;
+
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IoPriority {
+    class: IoPriorityClass,
+    level: u8,
+}
+
+impl IoPriority {
+    /// `level` ranges from 0 (highest) to 7 and is ignored for the idle class.
+    pub fn new(class: IoPriorityClass, level: u8) -> Self {
+        Self {
+            class,
+            level: level.min(7),
+        }
+    }
+    pub(crate) fn from_raw(io_priority: i32) -> Self {
+        let class = match io_priority >> IOPRIO_CLASS_SHIFT {
+            1 => IoPriorityClass::RealTime,
+            2 => IoPriorityClass::BestEffort,
+            3 => IoPriorityClass::Idle,
+            _ => IoPriorityClass::None,
+        };
+        Self {
+            class,
+            level: (io_priority & IOPRIO_PRIO_MASK) as u8,
+        }
+    }
+    pub(crate) fn as_raw(&self) -> i32 {
+        let class = match self.class {
+            IoPriorityClass::None => 0,
+            IoPriorityClass::RealTime => 1,
+            IoPriorityClass::BestEffort => 2,
+            IoPriorityClass::Idle => 3,
+        };
+        (class << IOPRIO_CLASS_SHIFT) | self.level as i32
+    }
+    pub fn get_class(&self) -> IoPriorityClass {
+        self.class
+    }
+    pub fn get_level(&self) -> u8 {
+        self.level
+    }
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessScheduling {
+    priority: i64,
+    nice: i64,
+    policy: SchedulingPolicy,
+    rt_priority: u32,
+}
+
+impl Default for ProcessScheduling {
+    fn default() -> Self {
+        Self {
+            priority: 0,
+            nice: 0,
+            policy: SchedulingPolicy::Other,
+            rt_priority: 0,
+        }
+    }
+}
+
+impl ProcessScheduling {
+    pub(crate) fn new(priority: i64, nice: i64, policy: i32, rt_priority: u32) -> Self {
+        Self {
+            priority,
+            nice,
+            policy: SchedulingPolicy::from_raw(policy),
+            rt_priority,
+        }
+    }
+    // `prio` of `/proc/<pid>/sched` is offset by 100 from the priority of `stat`
+    pub(crate) fn set_sched(&mut self, policy: i32, prio: i64) {
+        self.policy = SchedulingPolicy::from_raw(policy);
+        self.priority = prio - 100;
+    }
+    /// Kernel priority as shown by `top` in the PR column.
+    pub fn get_priority(&self) -> i64 {
+        self.priority
+    }
+    pub fn get_nice(&self) -> i64 {
+        self.nice
+    }
+    pub fn get_policy(&self) -> SchedulingPolicy {
+        self.policy
+    }
+    pub fn get_rt_priority(&self) -> u32 {
+        self.rt_priority
+    }
+}
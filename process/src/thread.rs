@@ -0,0 +1,138 @@
+use crate::get_proc_stat_fields;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Thread {
+    tid: u32,
+    name: String,
+    state: char,
+    cpu_used: f32,
+    processor: u32,
+}
+
+impl Thread {
+    pub fn get_tid(&self) -> u32 {
+        self.tid
+    }
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+    pub fn get_state(&self) -> char {
+        self.state
+    }
+    pub fn get_cpu_used(&self) -> f32 {
+        self.cpu_used
+    }
+    /// CPU the thread last ran on.
+    pub fn get_processor(&self) -> u32 {
+        self.processor
+    }
+}
+
+/// A thread read from `/proc/<pid>/task/<tid>/stat` along with its busy and start jiffies.
+#[cfg(target_os = "linux")]
+pub(crate) struct ThreadSample {
+    pub(crate) thread: Thread,
+    pub(crate) busy_time: u64,
+    pub(crate) starttime: u64,
+}
+
+#[cfg(target_os = "linux")]
+impl ThreadSample {
+    pub(crate) fn set_cpu_used(&mut self, cpu_used: f32) {
+        self.thread.cpu_used = cpu_used;
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn get_thread_sample(root: &Path, pid: u32, tid: u32) -> Result<ThreadSample> {
+    let buffer = fs::read_to_string(
+        root.join("proc")
+            .join(pid.to_string())
+            .join("task")
+            .join(tid.to_string())
+            .join("stat"),
+    )?;
+    let name = match (buffer.find('('), buffer.rfind(')')) {
+        (Some(start), Some(end)) if start < end => buffer[start + 1..end].to_string(),
+        _ => String::new(),
+    };
+    let stat_fields = get_proc_stat_fields(&buffer);
+    let field = |index: usize| stat_fields.get(index).copied().unwrap_or("0");
+    Ok(ThreadSample {
+        thread: Thread {
+            tid,
+            name,
+            state: field(0).chars().next().unwrap_or('?'),
+            cpu_used: 0f32,
+            processor: field(36).parse::<u32>()?,
+        },
+        busy_time: field(11).parse::<u64>()? + field(12).parse::<u64>()?,
+        starttime: field(19).parse::<u64>()?,
+    })
+}
+
+/// Threads that exit while the task directory is being read are left out.
+#[cfg(target_os = "linux")]
+pub(crate) fn get_thread_samples(root: &Path, pid: u32) -> Vec<ThreadSample> {
+    let mut thread_samples: Vec<ThreadSample> = Vec::new();
+    let Ok(task_dir) = fs::read_dir(root.join("proc").join(pid.to_string()).join("task")) else {
+        return thread_samples;
+    };
+    for task in task_dir.flatten() {
+        if let Some(Ok(tid)) = task.file_name().to_str().map(str::parse::<u32>) {
+            if let Ok(thread_sample) = get_thread_sample(root, pid, tid) {
+                thread_samples.push(thread_sample);
+            }
+        }
+    }
+    thread_samples.sort_unstable_by_key(|thread_sample| thread_sample.thread.tid);
+    thread_samples
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixture::{Fixture, FixtureProc};
+    use crate::System;
+
+    #[test]
+    fn threads_and_their_cpu_are_read_from_task() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        let mut proc = FixtureProc::new(42, 1);
+        proc.threads = 3;
+        proc.busy_time = (100, 50);
+        fixture.write_proc(&proc).unwrap();
+        let mut system = System::with_root(fixture.get_root());
+        system.refresh_system_info().unwrap();
+        let threads = system.get_proc_info(&42).unwrap().get_threads();
+        let tids: Vec<u32> = threads.iter().map(|thread| thread.get_tid()).collect();
+        assert_eq!(tids, proc.get_tids());
+        assert_eq!(threads[1].get_name(), "worker 42");
+        assert_eq!(threads[1].get_state(), 'S');
+        assert_eq!(threads[1].get_processor(), 2);
+
+        // 1000 jiffies pass, every thread spends 50 of them running
+        fixture
+            .write(
+                "proc/stat",
+                "cpu  4300 0 2200 90500 100 0 50 0 0 0\n\
+                 cpu0 1075 0 550 22625 25 0 12 0 0 0\n\
+                 cpu1 1075 0 550 22625 25 0 13 0 0 0\n\
+                 cpu2 1075 0 550 22625 25 0 12 0 0 0\n\
+                 cpu3 1075 0 550 22625 25 0 13 0 0 0\n",
+            )
+            .unwrap();
+        proc.busy_time = (130, 70);
+        fixture.write_stat(&proc).unwrap();
+        system.refresh_system_info().unwrap();
+        let threads = system.get_proc_info(&42).unwrap().get_threads();
+        assert_eq!(threads.len(), 3);
+        for thread in threads {
+            assert_eq!(thread.get_cpu_used(), 5f32);
+        }
+    }
+}
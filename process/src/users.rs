@@ -0,0 +1,107 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// User names by uid, `etc/passwd` is only read again when an unknown uid shows up or
+/// the file changed.
+#[derive(Clone, Default)]
+pub(crate) struct UserNames {
+    names: HashMap<u32, String>,
+    /// Modification time and length of passwd when the names were read
+    modified: Option<(SystemTime, u64)>,
+}
+
+impl UserNames {
+    /// Forgets every name, including the uid fallbacks, when passwd changed since the last
+    /// refresh.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn refresh(&mut self, root: &Path) {
+        let modified = fs::metadata(root.join("etc/passwd"))
+            .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+            .ok();
+        if modified != self.modified {
+            self.names.clear();
+            self.modified = modified;
+        }
+    }
+    /// Uids without a passwd entry, or without a readable passwd (e.g. a procfs capture
+    /// copied without `/etc`), are named by their number, which is remembered as well.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn get_name(&mut self, root: &Path, uid: u32) -> String {
+        if let Some(name) = self.names.get(&uid) {
+            return name.clone();
+        }
+        let mut names = get_passwd_names(root).unwrap_or_default();
+        let name = names.entry(uid).or_insert_with(|| uid.to_string()).clone();
+        // Keep the uids remembered without an entry, so passwd is not read for them again
+        for (uid, name) in self.names.drain() {
+            names.entry(uid).or_insert(name);
+        }
+        self.names = names;
+        name
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn get_passwd_names(root: &Path) -> Result<HashMap<u32, String>> {
+    let mut names: HashMap<u32, String> = HashMap::new();
+    let buffer = fs::read_to_string(root.join("etc/passwd"))?;
+    for line in buffer.lines() {
+        let mut fields = line.split(':');
+        if let (Some(name), Some(Ok(uid))) = (fields.next(), fields.nth(1).map(str::parse::<u32>)) {
+            names.entry(uid).or_insert_with(|| name.to_string());
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::{Fixture, FIRST_UID};
+
+    #[test]
+    fn names_come_from_passwd() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        let mut user_names = UserNames::default();
+        assert_eq!(
+            user_names.get_name(fixture.get_root(), FIRST_UID + 4),
+            "user4"
+        );
+        assert_eq!(user_names.get_name(fixture.get_root(), 5), "5");
+    }
+
+    #[test]
+    fn missing_passwd_falls_back_to_uids() {
+        let fixture = Fixture::new().unwrap();
+        let mut user_names = UserNames::default();
+        assert_eq!(user_names.get_name(fixture.get_root(), FIRST_UID), "1000");
+    }
+
+    #[test]
+    fn changed_passwd_is_read_again() {
+        let fixture = Fixture::new().unwrap();
+        fixture.write_system().unwrap();
+        let mut user_names = UserNames::default();
+        user_names.refresh(fixture.get_root());
+        assert_eq!(user_names.get_name(fixture.get_root(), FIRST_UID), "user0");
+        assert_eq!(user_names.get_name(fixture.get_root(), 5), "5");
+        // The length changes as well, so the test does not depend on the mtime granularity
+        fixture
+            .write(
+                "etc/passwd",
+                "renamed:x:1000:1000::/home/renamed:/bin/sh\nlate:x:5:5::/:/bin/sh\n",
+            )
+            .unwrap();
+        assert_eq!(user_names.get_name(fixture.get_root(), FIRST_UID), "user0");
+        user_names.refresh(fixture.get_root());
+        assert_eq!(
+            user_names.get_name(fixture.get_root(), FIRST_UID),
+            "renamed"
+        );
+        assert_eq!(user_names.get_name(fixture.get_root(), 5), "late");
+    }
+}